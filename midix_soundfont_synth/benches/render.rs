@@ -0,0 +1,85 @@
+//! Measures the rendering of many voices.
+//!
+//! Compare `cargo bench` with `cargo bench --no-default-features` to see the speed-up of SIMD.
+//! The `render_threads` group compares the rendering with worker threads.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use midix_soundfont_synth::prelude::*;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::create_sound_font;
+
+const SAMPLE_RATE: i32 = 48000;
+const FRAMES: usize = 1024;
+
+fn render(c: &mut Criterion) {
+    let sound_font = Arc::new(SoundFont::new(&mut &create_sound_font()[..]).unwrap());
+
+    let mut group = c.benchmark_group("render");
+    group.throughput(Throughput::Elements(FRAMES as u64));
+
+    for voices in [16, 64, 256] {
+        let mut settings = SynthesizerSettings::new(SAMPLE_RATE);
+        settings.maximum_polyphony = voices;
+        // The effects do not depend on the number of voices, so they are left out.
+        settings.enable_reverb_and_chorus = false;
+        let mut synthesizer = Synthesizer::new(sound_font.clone(), &settings).unwrap();
+        start_notes(&mut synthesizer, voices);
+
+        let mut left = vec![0_f32; FRAMES];
+        let mut right = vec![0_f32; FRAMES];
+        group.bench_with_input(BenchmarkId::from_parameter(voices), &voices, |b, _| {
+            b.iter(|| {
+                synthesizer.render(&mut left, &mut right);
+                black_box((&left, &right));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn render_threads(c: &mut Criterion) {
+    const VOICES: usize = 256;
+
+    let sound_font = Arc::new(SoundFont::new(&mut &create_sound_font()[..]).unwrap());
+
+    let mut group = c.benchmark_group("render_threads");
+    group.throughput(Throughput::Elements(FRAMES as u64));
+
+    for threads in [1, 2, 4] {
+        let mut settings = SynthesizerSettings::new(SAMPLE_RATE);
+        settings.maximum_polyphony = VOICES;
+        settings.enable_reverb_and_chorus = false;
+        settings.thread_count = threads;
+        let mut synthesizer = Synthesizer::new(sound_font.clone(), &settings).unwrap();
+        start_notes(&mut synthesizer, VOICES);
+
+        let mut left = vec![0_f32; FRAMES];
+        let mut right = vec![0_f32; FRAMES];
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, _| {
+            b.iter(|| {
+                synthesizer.render(&mut left, &mut right);
+                black_box((&left, &right));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn start_notes(synthesizer: &mut Synthesizer, voices: usize) {
+    for i in 0..voices {
+        let channel = (i % 16) as u8;
+        let key = 36 + (i / 16) as u8 * 3 + channel % 3;
+        synthesizer.note_on(channel, key, 100);
+    }
+}
+
+criterion_group!(benches, render, render_threads);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+use core::alloc::{GlobalAlloc, Layout};
+#[cfg(debug_assertions)]
+use core::cell::Cell;
+use std::alloc::System;
+
+#[cfg(doc)]
+use super::Synthesizer;
+
+// In debug builds, the synthesizer marks the calls which must be real-time safe,
+// and GuardedAllocator aborts if memory is allocated or freed within them.
+// The mark is per thread, so other threads can allocate freely.
+
+#[cfg(debug_assertions)]
+std::thread_local! {
+    static REAL_TIME_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// A global allocator which checks that the synthesizer does not allocate or free memory
+/// in its real-time calls: [`Synthesizer::render`], [`Synthesizer::render_multi`],
+/// [`Synthesizer::note_on`], [`Synthesizer::note_off`], [`Synthesizer::process_midi_message`]
+/// and [`Synthesizer::process_sysex`].
+///
+/// # Remarks
+///
+/// Install it with `#[global_allocator]`, wrapping the allocator which does the work,
+/// such as [`System`].
+/// The check is only done in debug builds, where the process is aborted if it fails.
+/// In release builds, the allocator only forwards to the inner one.
+pub struct GuardedAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> GuardedAllocator<A> {
+    /// Initializes a new allocator which forwards to another.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The allocator which does the work.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn check(&self) {
+        #[cfg(debug_assertions)]
+        if REAL_TIME_DEPTH.try_with(Cell::get).unwrap_or(0) > 0 {
+            // Reporting the failure may allocate, so the check is turned off first.
+            let _ = REAL_TIME_DEPTH.try_with(|depth| depth.set(0));
+            std::eprintln!("the synthesizer allocated or freed memory in a real-time call");
+            std::process::abort();
+        }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for GuardedAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.check();
+        unsafe { self.inner.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.check();
+        unsafe { self.inner.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.check();
+        unsafe { self.inner.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.check();
+        unsafe { self.inner.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Marks the current thread as being in a real-time call, until it is dropped.
+pub(crate) struct RealTimeSection {}
+
+impl RealTimeSection {
+    pub(crate) fn enter() -> Self {
+        #[cfg(debug_assertions)]
+        REAL_TIME_DEPTH.with(|depth| depth.set(depth.get() + 1));
+
+        Self {}
+    }
+}
+
+impl Drop for RealTimeSection {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        let _ = REAL_TIME_DEPTH.try_with(|depth| depth.set(depth.get().saturating_sub(1)));
+    }
+}
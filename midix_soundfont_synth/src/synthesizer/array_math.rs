@@ -1,17 +1,260 @@
+#[cfg(feature = "simd")]
+use wide::{f32x8, i32x8};
+
+// With the "simd" feature, the values are processed in vectors of LANES values,
+// and the scalar code handles what is left over.
+// Without it, the same functions are implemented with scalar code only.
+
 pub(crate) struct ArrayMath {}
 
 impl ArrayMath {
+    /// The number of values in a vector.
+    pub(crate) const LANES: usize = 8;
+
+    /// The number of fraction bits of the fixed-point positions used by `interpolate`.
+    pub(crate) const FRAC_BITS: i32 = 24;
+    const FRAC_UNIT: i64 = 1_i64 << ArrayMath::FRAC_BITS;
+    const FP_TO_FLOAT: f32 = 1_f32 / ArrayMath::FRAC_UNIT as f32;
+
     pub(crate) fn multiply_add(a: f32, x: &[f32], destination: &mut [f32]) {
+        #[cfg(feature = "simd")]
+        {
+            let mut x_chunks = x.chunks_exact(ArrayMath::LANES);
+            let mut destination_chunks = destination.chunks_exact_mut(ArrayMath::LANES);
+
+            let a_vector = f32x8::splat(a);
+            for (x, destination) in (&mut x_chunks).zip(&mut destination_chunks) {
+                let result = ArrayMath::load(destination) + a_vector * ArrayMath::load(x);
+                destination.copy_from_slice(&result.to_array());
+            }
+
+            ArrayMath::multiply_add_scalar(
+                a,
+                x_chunks.remainder(),
+                destination_chunks.into_remainder(),
+            );
+        }
+
+        #[cfg(not(feature = "simd"))]
+        ArrayMath::multiply_add_scalar(a, x, destination);
+    }
+
+    fn multiply_add_scalar(a: f32, x: &[f32], destination: &mut [f32]) {
         for (x, destination) in x.iter().zip(destination.iter_mut()) {
             *destination += a * *x;
         }
     }
 
     pub(crate) fn multiply_add_slope(a: f32, step: f32, x: &[f32], destination: &mut [f32]) {
+        #[cfg(feature = "simd")]
+        {
+            let mut x_chunks = x.chunks_exact(ArrayMath::LANES);
+            let mut destination_chunks = destination.chunks_exact_mut(ArrayMath::LANES);
+
+            let mut a_vector = f32x8::splat(a)
+                + f32x8::splat(step) * f32x8::from([0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+            let step_vector = f32x8::splat(step * ArrayMath::LANES as f32);
+            for (x, destination) in (&mut x_chunks).zip(&mut destination_chunks) {
+                let result = ArrayMath::load(destination) + a_vector * ArrayMath::load(x);
+                destination.copy_from_slice(&result.to_array());
+                a_vector += step_vector;
+            }
+
+            ArrayMath::multiply_add_slope_scalar(
+                a_vector.to_array()[0],
+                step,
+                x_chunks.remainder(),
+                destination_chunks.into_remainder(),
+            );
+        }
+
+        #[cfg(not(feature = "simd"))]
+        ArrayMath::multiply_add_slope_scalar(a, step, x, destination);
+    }
+
+    fn multiply_add_slope_scalar(a: f32, step: f32, x: &[f32], destination: &mut [f32]) {
         let mut a = a;
         for (x, destination) in x.iter().zip(destination.iter_mut()) {
             *destination += a * *x;
             a += step;
         }
     }
+
+    /// Reads `LANES` samples from the data with linear interpolation, and stores them scaled by
+    /// `scale`. The positions are fixed-point numbers with `FRAC_BITS` fraction bits,
+    /// starting at `position_fp` and advancing by `step_fp`.
+    /// The data must contain all the samples which are read.
+    pub(crate) fn interpolate(
+        data: &[i16],
+        position_fp: i64,
+        step_fp: i64,
+        scale: f32,
+        destination: &mut [f32; ArrayMath::LANES],
+    ) {
+        #[cfg(feature = "simd")]
+        {
+            // The samples are read with integer positions, and interpolated as a vector.
+            let mut x1 = [0_i32; ArrayMath::LANES];
+            let mut x2 = [0_i32; ArrayMath::LANES];
+            let mut a_fp = [0_i32; ArrayMath::LANES];
+            let mut position_fp = position_fp;
+            for ((x1, x2), a_fp) in x1.iter_mut().zip(x2.iter_mut()).zip(a_fp.iter_mut()) {
+                let index = (position_fp >> ArrayMath::FRAC_BITS) as usize;
+                *x1 = data[index] as i32;
+                *x2 = data[index + 1] as i32;
+                *a_fp = (position_fp & (ArrayMath::FRAC_UNIT - 1)) as i32;
+
+                position_fp += step_fp;
+            }
+
+            let x1 = i32x8::from(x1).round_float();
+            let x2 = i32x8::from(x2).round_float();
+            let a = f32x8::splat(ArrayMath::FP_TO_FLOAT) * i32x8::from(a_fp).round_float();
+            *destination = (f32x8::splat(scale) * (x1 + a * (x2 - x1))).to_array();
+        }
+
+        #[cfg(not(feature = "simd"))]
+        {
+            let scale = ArrayMath::FP_TO_FLOAT * scale;
+            let mut position_fp = position_fp;
+            for destination in destination.iter_mut() {
+                let index = (position_fp >> ArrayMath::FRAC_BITS) as usize;
+                let x1 = data[index] as i64;
+                let x2 = data[index + 1] as i64;
+                let a_fp = position_fp & (ArrayMath::FRAC_UNIT - 1);
+                *destination = scale * ((x1 << ArrayMath::FRAC_BITS) + a_fp * (x2 - x1)) as f32;
+
+                position_fp += step_fp;
+            }
+        }
+    }
+
+    /// Computes `a0 * x0 + a1 * x1 + a2 * x2`.
+    pub(crate) fn multiply_add3(
+        a0: f32,
+        x0: &[f32; ArrayMath::LANES],
+        a1: f32,
+        x1: &[f32; ArrayMath::LANES],
+        a2: f32,
+        x2: &[f32; ArrayMath::LANES],
+    ) -> [f32; ArrayMath::LANES] {
+        #[cfg(feature = "simd")]
+        {
+            let result = f32x8::splat(a0) * f32x8::from(*x0)
+                + f32x8::splat(a1) * f32x8::from(*x1)
+                + f32x8::splat(a2) * f32x8::from(*x2);
+            result.to_array()
+        }
+
+        #[cfg(not(feature = "simd"))]
+        core::array::from_fn(|i| a0 * x0[i] + a1 * x1[i] + a2 * x2[i])
+    }
+
+    #[cfg(feature = "simd")]
+    fn load(x: &[f32]) -> f32x8 {
+        let mut array = [0_f32; ArrayMath::LANES];
+        array.copy_from_slice(x);
+        f32x8::from(array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Lengths which are and are not a multiple of LANES.
+    const LENGTHS: [usize; 7] = [0, 1, 7, 8, 13, 64, 67];
+
+    fn create_signal(length: usize, seed: f32) -> Vec<f32> {
+        (0..length)
+            .map(|i| (seed * (i as f32 + 1_f32)).sin())
+            .collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32], epsilon: f32) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+            assert!(
+                (actual - expected).abs() <= epsilon,
+                "index {i}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn multiply_add_matches_scalar() {
+        for length in LENGTHS {
+            let x = create_signal(length, 0.7_f32);
+            let mut actual = create_signal(length, 1.3_f32);
+            let mut expected = actual.clone();
+
+            ArrayMath::multiply_add(0.3_f32, &x, &mut actual);
+            ArrayMath::multiply_add_scalar(0.3_f32, &x, &mut expected);
+
+            assert_close(&actual, &expected, 1.0E-6_f32);
+        }
+    }
+
+    #[test]
+    fn multiply_add_slope_matches_scalar() {
+        for length in LENGTHS {
+            let x = create_signal(length, 0.7_f32);
+            let mut actual = create_signal(length, 1.3_f32);
+            let mut expected = actual.clone();
+
+            ArrayMath::multiply_add_slope(0.9_f32, -0.01_f32, &x, &mut actual);
+            ArrayMath::multiply_add_slope_scalar(0.9_f32, -0.01_f32, &x, &mut expected);
+
+            // The vectors compute the slope with one multiplication instead of repeated additions.
+            assert_close(&actual, &expected, 1.0E-5_f32);
+        }
+    }
+
+    #[test]
+    fn multiply_add3_matches_scalar() {
+        let x0 = [0.1_f32, -0.2, 0.3, -0.4, 0.5, -0.6, 0.7, -0.8];
+        let x1 = [1_f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let x2 = [-1_f32, 0.5, -0.25, 0.125, -1.0, 0.5, -0.25, 0.125];
+
+        let actual = ArrayMath::multiply_add3(0.5_f32, &x0, -0.25_f32, &x1, 2_f32, &x2);
+        let expected: Vec<f32> = (0..ArrayMath::LANES)
+            .map(|i| 0.5_f32 * x0[i] - 0.25_f32 * x1[i] + 2_f32 * x2[i])
+            .collect();
+
+        assert_close(&actual, &expected, 1.0E-6_f32);
+    }
+
+    #[test]
+    fn interpolate_matches_scalar() {
+        let data: Vec<i16> = (0..256)
+            .map(|i| ((i as f32 * 0.37_f32).sin() * 30000_f32) as i16)
+            .collect();
+        let scale = 1_f32 / 32768_f32;
+
+        // Steps below, at and above one sample, including a fraction which is not exact in binary.
+        let steps = [0.25_f64, 1.0, 1.37, 2.9];
+        for step in steps {
+            let step_fp = (step * ArrayMath::FRAC_UNIT as f64) as i64;
+            let mut position_fp = 3 * ArrayMath::FRAC_UNIT / 7;
+            while ((position_fp + 8 * step_fp) >> ArrayMath::FRAC_BITS) + 1 < data.len() as i64 {
+                let mut actual = [0_f32; ArrayMath::LANES];
+                ArrayMath::interpolate(&data, position_fp, step_fp, scale, &mut actual);
+
+                let expected: Vec<f32> = (0..ArrayMath::LANES as i64)
+                    .map(|i| {
+                        let position_fp = position_fp + i * step_fp;
+                        let index = (position_fp >> ArrayMath::FRAC_BITS) as usize;
+                        let a = (position_fp & (ArrayMath::FRAC_UNIT - 1)) as f64
+                            / ArrayMath::FRAC_UNIT as f64;
+                        let x1 = data[index] as f64;
+                        let x2 = data[index + 1] as f64;
+                        (scale as f64 * (x1 + a * (x2 - x1))) as f32
+                    })
+                    .collect();
+
+                assert_close(&actual, &expected, 1.0E-6_f32);
+                position_fp += 5 * step_fp;
+            }
+        }
+    }
 }
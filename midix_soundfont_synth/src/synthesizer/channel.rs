@@ -1,3 +1,8 @@
+use bevy_platform::prelude::*;
+
+use super::{ControllerDestination, MpeZone, NrpnParameters, Tuning, voice::GeneratorOffsets};
+use crate::utils;
+
 #[derive(PartialEq, Eq)]
 enum DataType {
     None,
@@ -11,26 +16,98 @@ pub(crate) struct SynthChannel {
     bank_number: u8,
     patch_number: u8,
 
-    modulation: u16,
-    volume: u16,
-    pan: u16,
-    expression: u16,
+    // Continuous controllers are kept at the 32-bit resolution of MIDI 2.0.
+    // MIDI 1.0 values are scaled up on arrival.
+    modulation: u32,
+    volume: u32,
+    pan: u32,
+    expression: u32,
     hold_pedal: bool,
+    sostenuto: bool,
+    soft_pedal: bool,
+
+    portamento: bool,
+    portamento_time: u16,
+    portamento_control: Option<u8>,
+    mono_mode: bool,
+    last_key: Option<u8>,
+    held_keys: Vec<u8>,
+
+    brightness: u32,
+
+    channel_pressure: u32,
+    key_pressure: [u32; 128],
+    channel_pressure_destination: ControllerDestination,
+    key_pressure_destination: ControllerDestination,
 
     reverb_send: u8,
     chorus_send: u8,
 
     rpn: u16,
+    nrpn: u16,
+    nrpn_parameters: NrpnParameters,
     pitch_bend_range: u16,
-    coarse_tune: u16,
+    coarse_tune: i8,
     fine_tune: u16,
+    tuning_program: u8,
+    tuning_bank: u8,
+    tuning: Tuning,
+    modulation_depth_range: u16,
 
     pitch_bend: f32,
+    per_note: [PerNoteControllers; 128],
 
     last_data_type: DataType,
+
+    mpe_master: Option<u8>,
+
+    maximum_polyphony: Option<usize>,
+    reserved_voices: usize,
+
+    // Set by the host rather than by MIDI messages, so they survive resets.
+    transpose: i32,
+    drum_key_map: [u8; 128],
 }
 
 impl SynthChannel {
+    /// NRPN (MSB 0x7E, LSB 0x00): the maximum number of voices for the channel.
+    pub(crate) const NRPN_MAXIMUM_POLYPHONY: u16 = 0x7E << 7;
+    /// NRPN (MSB 0x7E, LSB 0x01): the number of voices reserved for the channel.
+    pub(crate) const NRPN_RESERVED_VOICES: u16 = (0x7E << 7) | 0x01;
+
+    /// The RPN of the MPE configuration message.
+    pub(crate) const RPN_MPE_CONFIGURATION: u16 = 6;
+    /// The RPN (and NRPN) number which deselects the current parameter.
+    const NULL_PARAMETER: u16 = 0x3FFF;
+
+    /// The default modulation depth range, 50 cents (MSB 0x00, LSB 0x40).
+    const DEFAULT_MODULATION_DEPTH_RANGE: u16 = 0x40;
+
+    /// The center value of 32-bit bipolar controllers, such as pan or pitch bend.
+    const CENTER: u32 = 0x8000_0000;
+
+    /// The default pitch bend range of per-note pitch bend in semitones.
+    pub(crate) const PER_NOTE_PITCH_BEND_RANGE: f32 = 48_f32;
+
+    /// The filter cutoff change in cents when the brightness is at its maximum.
+    pub(crate) const BRIGHTNESS_RANGE: f32 = 2400_f32;
+
+    /// The velocity scale applied by the soft pedal, out of 128.
+    const SOFT_PEDAL_VELOCITY_SCALE: u32 = 96;
+
+    const IDENTITY_KEY_MAP: [u8; 128] = {
+        let mut map = [0_u8; 128];
+        let mut key = 0;
+        while key < 128 {
+            map[key] = key as u8;
+            key += 1;
+        }
+        map
+    };
+
+    /// The portamento time in seconds when CC 5 is at its maximum.
+    const MAXIMUM_PORTAMENTO_TIME: f32 = 8_f32;
+
     pub(crate) fn new(is_percussion_channel: bool) -> Self {
         let mut channel = Self {
             is_percussion_channel,
@@ -41,14 +118,39 @@ impl SynthChannel {
             pan: 0,
             expression: 0,
             hold_pedal: false,
+            sostenuto: false,
+            soft_pedal: false,
+            portamento: false,
+            portamento_time: 0,
+            portamento_control: None,
+            mono_mode: false,
+            last_key: None,
+            held_keys: Vec::with_capacity(128),
+            brightness: 0,
+            channel_pressure: 0,
+            key_pressure: [0; 128],
+            channel_pressure_destination: ControllerDestination::PRESSURE_DEFAULT,
+            key_pressure_destination: ControllerDestination::PRESSURE_DEFAULT,
             reverb_send: 0,
             chorus_send: 0,
             rpn: 0,
+            nrpn: 0,
+            nrpn_parameters: NrpnParameters::DEFAULT,
             pitch_bend_range: 0,
             coarse_tune: 0,
             fine_tune: 0,
+            tuning_program: 0,
+            tuning_bank: 0,
+            tuning: Tuning::EQUAL_TEMPERAMENT,
+            modulation_depth_range: 0,
             pitch_bend: 0_f32,
+            per_note: [PerNoteControllers::DEFAULT; 128],
             last_data_type: DataType::None,
+            mpe_master: None,
+            maximum_polyphony: None,
+            reserved_voices: 0,
+            transpose: 0,
+            drum_key_map: SynthChannel::IDENTITY_KEY_MAP,
         };
 
         channel.reset();
@@ -61,32 +163,67 @@ impl SynthChannel {
         self.patch_number = 0;
 
         self.modulation = 0;
-        self.volume = 100 << 7;
-        self.pan = 64 << 7;
-        self.expression = 127 << 7;
+        self.volume = utils::scale_up(100 << 7, 14, 32);
+        self.pan = utils::scale_up(64 << 7, 14, 32);
+        self.expression = u32::MAX;
         self.hold_pedal = false;
+        self.sostenuto = false;
+        self.soft_pedal = false;
+
+        self.portamento = false;
+        self.portamento_time = 0;
+        self.portamento_control = None;
+        self.mono_mode = false;
+        self.last_key = None;
+        self.held_keys.clear();
+
+        self.brightness = SynthChannel::CENTER;
+
+        self.channel_pressure = 0;
+        self.key_pressure = [0; 128];
+        self.channel_pressure_destination = ControllerDestination::PRESSURE_DEFAULT;
+        self.key_pressure_destination = ControllerDestination::PRESSURE_DEFAULT;
 
         self.reverb_send = 40;
         self.chorus_send = 0;
 
         self.rpn = 0xFFFF;
+        self.nrpn = 0xFFFF;
+        self.nrpn_parameters = NrpnParameters::DEFAULT;
         self.pitch_bend_range = 2 << 7;
         self.coarse_tune = 0;
         self.fine_tune = 8192;
+        self.tuning_program = 0;
+        self.tuning_bank = 0;
+        self.tuning = Tuning::EQUAL_TEMPERAMENT;
+        self.modulation_depth_range = SynthChannel::DEFAULT_MODULATION_DEPTH_RANGE;
 
         self.pitch_bend = 0_f32;
+        self.per_note = [PerNoteControllers::DEFAULT; 128];
+
+        // Member channels of an MPE zone keep their own defaults.
+        self.set_mpe_master(self.mpe_master);
     }
 
     pub(crate) fn reset_all_controllers(&mut self) {
         self.modulation = 0;
 
-        self.expression = 127 << 7;
+        self.expression = u32::MAX;
         self.hold_pedal = false;
+        self.sostenuto = false;
+        self.soft_pedal = false;
+        self.portamento = false;
+        self.portamento_control = None;
+
+        self.channel_pressure = 0;
+        self.key_pressure = [0; 128];
 
         self.rpn = 0xFFFF;
-        self.pitch_bend_range = 2 << 7;
+        self.nrpn = 0xFFFF;
+        self.pitch_bend_range = self.get_default_pitch_bend_range();
 
         self.pitch_bend = 0_f32;
+        self.per_note = [PerNoteControllers::DEFAULT; 128];
     }
 
     pub(crate) fn set_bank(&mut self, value: u8) {
@@ -97,46 +234,193 @@ impl SynthChannel {
         }
     }
 
+    /// Makes the channel a percussion channel or a melodic one, keeping the selected bank.
+    pub(crate) fn set_percussion_channel(&mut self, value: bool) {
+        self.is_percussion_channel = value;
+        self.bank_number = (self.bank_number & 0x7F) | if value { 128 } else { 0 };
+    }
+
+    pub(crate) fn get_percussion_channel(&self) -> bool {
+        self.is_percussion_channel
+    }
+
+    pub(crate) fn set_drum_key(&mut self, key: u8, drum_key: u8) {
+        self.drum_key_map[key as usize & 0x7F] = drum_key & 0x7F;
+    }
+
+    pub(crate) fn get_drum_key(&self, key: u8) -> u8 {
+        self.drum_key_map[key as usize & 0x7F]
+    }
+
+    pub(crate) fn clear_drum_key_map(&mut self) {
+        self.drum_key_map = SynthChannel::IDENTITY_KEY_MAP;
+    }
+
+    /// Gets the key which an incoming key plays.
+    /// Only percussion channels remap their keys.
+    pub(crate) fn get_mapped_key(&self, key: u8) -> u8 {
+        if self.is_percussion_channel {
+            self.get_drum_key(key)
+        } else {
+            key
+        }
+    }
+
     pub(crate) fn set_patch(&mut self, value: u8) {
         self.patch_number = value;
     }
 
     pub(crate) fn set_modulation_coarse(&mut self, value: u8) {
-        self.modulation = (self.modulation & 0x7F) | ((value as u16) << 7);
+        self.modulation = SynthChannel::with_coarse(self.modulation, value);
     }
 
     pub(crate) fn set_modulation_fine(&mut self, value: u8) {
-        self.modulation = (self.modulation & 0xFF80) | value as u16;
+        self.modulation = SynthChannel::with_fine(self.modulation, value);
+    }
+
+    pub(crate) fn set_modulation(&mut self, value: u32) {
+        self.modulation = value;
     }
 
     pub(crate) fn set_volume_coarse(&mut self, value: u8) {
-        self.volume = (self.volume & 0x7F) | ((value as u16) << 7);
+        self.volume = SynthChannel::with_coarse(self.volume, value);
     }
 
     pub(crate) fn set_volume_fine(&mut self, value: u8) {
-        self.volume = (self.volume & 0xFF80) | value as u16;
+        self.volume = SynthChannel::with_fine(self.volume, value);
+    }
+
+    pub(crate) fn set_volume(&mut self, value: u32) {
+        self.volume = value;
     }
 
     pub(crate) fn set_pan_coarse(&mut self, value: u8) {
-        self.pan = (self.pan & 0x7F) | ((value as u16) << 7);
+        self.pan = SynthChannel::with_coarse(self.pan, value);
     }
 
     pub(crate) fn set_pan_fine(&mut self, value: u8) {
-        self.pan = (self.pan & 0xFF80) | value as u16;
+        self.pan = SynthChannel::with_fine(self.pan, value);
+    }
+
+    pub(crate) fn set_pan(&mut self, value: u32) {
+        self.pan = value;
     }
 
     pub(crate) fn set_expression_coarse(&mut self, value: u8) {
-        self.expression = (self.expression & 0x7F) | ((value as u16) << 7);
+        self.expression = SynthChannel::with_coarse(self.expression, value);
     }
 
     pub(crate) fn set_expression_fine(&mut self, value: u8) {
-        self.expression = (self.expression & 0xFF80) | value as u16;
+        self.expression = SynthChannel::with_fine(self.expression, value);
+    }
+
+    pub(crate) fn set_expression(&mut self, value: u32) {
+        self.expression = value;
+    }
+
+    /// Replaces the MSB of a 32-bit controller, treating it as a 14-bit MIDI 1.0 value.
+    fn with_coarse(value: u32, msb: u8) -> u32 {
+        let lsb = (value >> 18) & 0x7F;
+        utils::scale_up(((msb as u32 & 0x7F) << 7) | lsb, 14, 32)
+    }
+
+    /// Replaces the LSB of a 32-bit controller, treating it as a 14-bit MIDI 1.0 value.
+    fn with_fine(value: u32, lsb: u8) -> u32 {
+        let msb = value >> 25;
+        utils::scale_up((msb << 7) | (lsb as u32 & 0x7F), 14, 32)
     }
 
     pub(crate) fn set_hold_pedal(&mut self, value: u8) {
         self.hold_pedal = value >= 64;
     }
 
+    pub(crate) fn set_sostenuto(&mut self, value: bool) {
+        self.sostenuto = value;
+    }
+
+    pub(crate) fn set_soft_pedal(&mut self, value: u8) {
+        self.soft_pedal = value >= 64;
+    }
+
+    pub(crate) fn set_portamento(&mut self, value: u8) {
+        self.portamento = value >= 64;
+    }
+
+    pub(crate) fn set_portamento_time_coarse(&mut self, value: u8) {
+        self.portamento_time = (self.portamento_time & 0x7F) | ((value as u16) << 7);
+    }
+
+    pub(crate) fn set_portamento_time_fine(&mut self, value: u8) {
+        self.portamento_time = (self.portamento_time & 0xFF80) | value as u16;
+    }
+
+    pub(crate) fn set_portamento_control(&mut self, value: u8) {
+        self.portamento_control = Some(value);
+    }
+
+    pub(crate) fn set_mono_mode(&mut self, value: bool) {
+        self.mono_mode = value;
+        self.held_keys.clear();
+    }
+
+    /// Registers a new note and returns the key its pitch should glide from, if any.
+    /// The notes are kept by the key of the note-on, before the drum key map.
+    ///
+    /// A pending portamento control (CC 84) always wins, and is consumed by the note.
+    /// Otherwise, the note glides from the previous note if portamento is on.
+    pub(crate) fn start_note(&mut self, key: u8) -> Option<u8> {
+        let glide_from = match self.portamento_control.take() {
+            Some(source) => Some(source),
+            None if self.portamento => self.last_key,
+            None => None,
+        };
+        self.last_key = Some(self.get_mapped_key(key));
+
+        if self.mono_mode {
+            self.held_keys.retain(|held| *held != key);
+            self.held_keys.push(key);
+        }
+
+        glide_from
+    }
+
+    /// Unregisters a note in mono mode, and returns the key still held down
+    /// which should sound instead, if any.
+    pub(crate) fn stop_note(&mut self, key: u8) -> Option<u8> {
+        let was_sounding = self.held_keys.last() == Some(&key);
+        self.held_keys.retain(|held| *held != key);
+
+        if was_sounding {
+            let previous = self.held_keys.last().copied();
+            if let Some(previous) = previous {
+                self.last_key = Some(self.get_mapped_key(previous));
+            }
+            previous
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn set_brightness(&mut self, value: u32) {
+        self.brightness = value;
+    }
+
+    pub(crate) fn set_channel_pressure(&mut self, value: u32) {
+        self.channel_pressure = value;
+    }
+
+    pub(crate) fn set_key_pressure(&mut self, key: u8, value: u32) {
+        self.key_pressure[key as usize & 0x7F] = value;
+    }
+
+    pub(crate) fn set_channel_pressure_destination(&mut self, value: ControllerDestination) {
+        self.channel_pressure_destination = value;
+    }
+
+    pub(crate) fn set_key_pressure_destination(&mut self, value: ControllerDestination) {
+        self.key_pressure_destination = value;
+    }
+
     pub(crate) fn set_reverb_send(&mut self, value: u8) {
         self.reverb_send = value;
     }
@@ -147,34 +431,60 @@ impl SynthChannel {
 
     pub(crate) fn set_rpn_coarse(&mut self, value: u8) {
         self.rpn = (self.rpn & 0x7F) | ((value as u16) << 7);
-        self.last_data_type = DataType::Rpn;
+        self.last_data_type = SynthChannel::select_parameter(self.rpn, DataType::Rpn);
     }
 
     pub(crate) fn set_rpn_fine(&mut self, value: u8) {
         self.rpn = (self.rpn & 0xFF80) | value as u16;
-        self.last_data_type = DataType::Rpn;
+        self.last_data_type = SynthChannel::select_parameter(self.rpn, DataType::Rpn);
+    }
+
+    pub(crate) fn set_nrpn_coarse(&mut self, value: u8) {
+        self.nrpn = (self.nrpn & 0x7F) | ((value as u16) << 7);
+        self.last_data_type = SynthChannel::select_parameter(self.nrpn, DataType::Nrpn);
     }
 
-    pub(crate) fn set_nrpn_coarse(&mut self) {
-        self.last_data_type = DataType::Nrpn;
+    pub(crate) fn set_nrpn_fine(&mut self, value: u8) {
+        self.nrpn = (self.nrpn & 0xFF80) | value as u16;
+        self.last_data_type = SynthChannel::select_parameter(self.nrpn, DataType::Nrpn);
+    }
+
+    /// Data entry is disabled until another parameter is selected after the null parameter.
+    fn select_parameter(number: u16, data_type: DataType) -> DataType {
+        if number == SynthChannel::NULL_PARAMETER {
+            DataType::None
+        } else {
+            data_type
+        }
     }
 
-    pub(crate) fn set_nrpn_fine(&mut self) {
-        self.last_data_type = DataType::Nrpn;
+    /// Gets the RPN which data entry currently applies to, if any.
+    pub(crate) fn get_rpn(&self) -> Option<u16> {
+        (self.last_data_type == DataType::Rpn).then_some(self.rpn)
     }
 
     pub(crate) fn data_entry_coarse(&mut self, value: u8) {
+        if self.last_data_type == DataType::Nrpn {
+            self.nrpn_data_entry_coarse(value);
+            return;
+        }
+
         if self.last_data_type != DataType::Rpn {
             return;
         }
 
-        if self.rpn == 0 {
-            self.pitch_bend_range = (self.pitch_bend_range & 0x7F) | ((value as u16) << 7);
-        } else if self.rpn == 1 {
-            self.fine_tune = (self.fine_tune & 0x7F) | ((value as u16) << 7);
-        } else if self.rpn == 2 {
-            //TODO: what
-            self.coarse_tune = (value as u16) - 64;
+        match self.rpn {
+            0 => self.pitch_bend_range = (self.pitch_bend_range & 0x7F) | ((value as u16) << 7),
+            1 => self.fine_tune = (self.fine_tune & 0x7F) | ((value as u16) << 7),
+            // Coarse tuning is in semitones with the center at 64.
+            2 => self.coarse_tune = (value & 0x7F) as i8 - 64,
+            3 => self.tuning_program = value & 0x7F,
+            4 => self.tuning_bank = value & 0x7F,
+            5 => {
+                self.modulation_depth_range =
+                    (self.modulation_depth_range & 0x7F) | ((value as u16) << 7)
+            }
+            _ => (),
         }
     }
 
@@ -183,17 +493,160 @@ impl SynthChannel {
             return;
         }
 
-        if self.rpn == 0 {
-            self.pitch_bend_range = (self.pitch_bend_range & 0xFF80) | value as u16;
-        } else if self.rpn == 1 {
-            self.fine_tune = (self.fine_tune & 0xFF80) | value as u16;
+        match self.rpn {
+            0 => self.pitch_bend_range = (self.pitch_bend_range & 0xFF80) | value as u16,
+            1 => self.fine_tune = (self.fine_tune & 0xFF80) | value as u16,
+            5 => {
+                self.modulation_depth_range = (self.modulation_depth_range & 0xFF80) | value as u16
+            }
+            _ => (),
+        }
+    }
+
+    /// Steps the selected RPN up or down by one (Data Increment/Decrement).
+    ///
+    /// The pitch bend range, fine tuning and modulation depth range step by their LSB,
+    /// while the other parameters step by their only value.
+    pub(crate) fn data_increment(&mut self, up: bool) {
+        if self.last_data_type != DataType::Rpn {
+            return;
+        }
+
+        let step14 = |value: u16| {
+            if up {
+                (value + 1).min(0x3FFF)
+            } else {
+                value.saturating_sub(1)
+            }
+        };
+        let step7 = |value: u8| {
+            if up {
+                (value + 1).min(0x7F)
+            } else {
+                value.saturating_sub(1)
+            }
+        };
+
+        match self.rpn {
+            0 => self.pitch_bend_range = step14(self.pitch_bend_range),
+            1 => self.fine_tune = step14(self.fine_tune),
+            2 => self.coarse_tune = step7((self.coarse_tune + 64) as u8) as i8 - 64,
+            3 => self.tuning_program = step7(self.tuning_program),
+            4 => self.tuning_bank = step7(self.tuning_bank),
+            5 => self.modulation_depth_range = step14(self.modulation_depth_range),
+            _ => (),
+        }
+    }
+
+    fn nrpn_data_entry_coarse(&mut self, value: u8) {
+        if self.nrpn == SynthChannel::NRPN_MAXIMUM_POLYPHONY {
+            // Zero removes the limit.
+            self.maximum_polyphony = (value > 0).then_some(value as usize);
+        } else if self.nrpn == SynthChannel::NRPN_RESERVED_VOICES {
+            self.reserved_voices = value as usize;
+        } else {
+            self.nrpn_parameters.set(self.nrpn, value);
         }
     }
 
+    pub(crate) fn set_maximum_polyphony(&mut self, value: Option<usize>) {
+        self.maximum_polyphony = value;
+    }
+
+    pub(crate) fn get_transpose(&self) -> i32 {
+        self.transpose
+    }
+
+    pub(crate) fn set_transpose(&mut self, value: i32) {
+        self.transpose = value;
+    }
+
+    pub(crate) fn set_reserved_voices(&mut self, value: usize) {
+        self.reserved_voices = value;
+    }
+
+    pub(crate) fn get_pitch_bend_range_data(&self) -> u16 {
+        self.pitch_bend_range
+    }
+
+    pub(crate) fn set_pitch_bend_range_data(&mut self, value: u16) {
+        self.pitch_bend_range = value;
+    }
+
+    /// Makes the channel a member channel of the MPE zone with the given master channel,
+    /// or a regular channel if `None`, and applies the matching defaults.
+    pub(crate) fn set_mpe_master(&mut self, value: Option<u8>) {
+        self.mpe_master = value;
+
+        self.pitch_bend_range = self.get_default_pitch_bend_range();
+        self.channel_pressure_destination = if value.is_some() {
+            ControllerDestination::MPE_PRESSURE_DEFAULT
+        } else {
+            ControllerDestination::PRESSURE_DEFAULT
+        };
+    }
+
+    fn get_default_pitch_bend_range(&self) -> u16 {
+        if self.mpe_master.is_some() {
+            (MpeZone::DEFAULT_MEMBER_PITCH_BEND_RANGE as u16) << 7
+        } else {
+            (MpeZone::DEFAULT_MASTER_PITCH_BEND_RANGE as u16) << 7
+        }
+    }
+
+    pub(crate) fn get_mpe_master(&self) -> Option<u8> {
+        self.mpe_master
+    }
+
     pub(crate) fn set_pitch_bend(&mut self, lsb: u8, msb: u8) {
         self.pitch_bend = (1_f32 / 8192_f32) * ((lsb as i32 | ((msb as i32) << 7)) - 8192) as f32;
     }
 
+    pub(crate) fn set_pitch_bend_32(&mut self, value: u32) {
+        self.pitch_bend = SynthChannel::to_bipolar(value);
+    }
+
+    pub(crate) fn set_per_note_pitch_bend(&mut self, key: u8, value: u32) {
+        self.per_note[key as usize & 0x7F].pitch_bend = SynthChannel::to_bipolar(value);
+    }
+
+    /// Applies a MIDI 2.0 registered per-note controller.
+    /// Unsupported controllers are ignored.
+    pub(crate) fn set_registered_per_note_controller(&mut self, key: u8, index: u8, value: u32) {
+        let per_note = &mut self.per_note[key as usize & 0x7F];
+        match index {
+            1 => per_note.modulation = SynthChannel::to_unipolar(value),
+            // Pitch 7.25: the absolute pitch of the note in semitones.
+            3 => per_note.pitch = Some((1_f64 / (1 << 25) as f64 * value as f64) as f32),
+            7 => per_note.volume = SynthChannel::to_unipolar(value),
+            10 => per_note.pan = SynthChannel::to_bipolar(value),
+            11 => per_note.expression = SynthChannel::to_unipolar(value),
+            74 => per_note.brightness = SynthChannel::to_bipolar(value),
+            _ => (),
+        }
+    }
+
+    /// Sets the absolute pitch of the note in semitones, or `None` to follow its key.
+    pub(crate) fn set_per_note_pitch(&mut self, key: u8, value: Option<f32>) {
+        self.per_note[key as usize & 0x7F].pitch = value;
+    }
+
+    pub(crate) fn reset_per_note_controllers(&mut self, key: u8) {
+        self.per_note[key as usize & 0x7F] = PerNoteControllers::DEFAULT;
+    }
+
+    pub(crate) fn get_per_note_controllers(&self, key: u8) -> &PerNoteControllers {
+        &self.per_note[key as usize & 0x7F]
+    }
+
+    fn to_unipolar(value: u32) -> f32 {
+        (1_f64 / u32::MAX as f64 * value as f64) as f32
+    }
+
+    fn to_bipolar(value: u32) -> f32 {
+        (1_f64 / SynthChannel::CENTER as f64 * (value as f64 - SynthChannel::CENTER as f64)) as f32
+    }
+
     pub(crate) fn get_bank_number(&self) -> u8 {
         self.bank_number
     }
@@ -203,25 +656,93 @@ impl SynthChannel {
     }
 
     pub(crate) fn get_modulation(&self) -> f32 {
-        (50_f32 / 16383_f32) * self.modulation as f32
+        self.get_modulation_depth_range() * SynthChannel::to_unipolar(self.modulation)
+    }
+
+    /// Gets the vibrato depth in cents when the modulation is at its maximum.
+    /// The LSB of the RPN is in units of 100/128 cents.
+    pub(crate) fn get_modulation_depth_range(&self) -> f32 {
+        100_f32 * (self.modulation_depth_range >> 7) as f32
+            + (100_f32 / 128_f32) * (self.modulation_depth_range & 0x7F) as f32
     }
 
     pub(crate) fn get_volume(&self) -> f32 {
-        (1_f32 / 16383_f32) * self.volume as f32
+        SynthChannel::to_unipolar(self.volume)
     }
 
     pub(crate) fn get_pan(&self) -> f32 {
-        (100_f32 / 16383_f32) * self.pan as f32 - 50_f32
+        100_f32 * SynthChannel::to_unipolar(self.pan) - 50_f32
     }
 
     pub(crate) fn get_expression(&self) -> f32 {
-        (1_f32 / 16383_f32) * self.expression as f32
+        SynthChannel::to_unipolar(self.expression)
     }
 
     pub(crate) fn get_hold_pedal(&self) -> bool {
         self.hold_pedal
     }
 
+    pub(crate) fn get_sostenuto(&self) -> bool {
+        self.sostenuto
+    }
+
+    pub(crate) fn get_soft_pedal(&self) -> bool {
+        self.soft_pedal
+    }
+
+    /// Gets the velocity of a note played on this channel.
+    /// The soft pedal plays notes quieter, which also selects softer velocity layers.
+    pub(crate) fn get_note_velocity(&self, velocity: u16) -> u16 {
+        if self.soft_pedal {
+            ((velocity as u32 * SynthChannel::SOFT_PEDAL_VELOCITY_SCALE) >> 7).max(1) as u16
+        } else {
+            velocity
+        }
+    }
+
+    pub(crate) fn get_portamento(&self) -> bool {
+        self.portamento
+    }
+
+    /// Gets the portamento time in seconds.
+    pub(crate) fn get_portamento_time(&self) -> f32 {
+        // The curve is quadratic so that small values stay usable for fast slides.
+        let x = (1_f32 / 16383_f32) * self.portamento_time as f32;
+        SynthChannel::MAXIMUM_PORTAMENTO_TIME * x * x
+    }
+
+    pub(crate) fn get_mono_mode(&self) -> bool {
+        self.mono_mode
+    }
+
+    /// Gets the filter cutoff change in cents set by the brightness (CC 74).
+    pub(crate) fn get_brightness(&self) -> f32 {
+        SynthChannel::BRIGHTNESS_RANGE * SynthChannel::to_bipolar(self.brightness)
+    }
+
+    pub(crate) fn get_channel_pressure(&self) -> f32 {
+        SynthChannel::to_unipolar(self.channel_pressure)
+    }
+
+    pub(crate) fn get_key_pressure(&self, key: u8) -> f32 {
+        SynthChannel::to_unipolar(self.key_pressure[key as usize & 0x7F])
+    }
+
+    /// Sums up the modulation applied by the pressure controllers to the note.
+    pub(crate) fn get_pressure_modulation(&self, key: u8) -> ControllerDestination {
+        let channel = self.get_channel_pressure();
+        let key_pressure = self.get_key_pressure(key);
+        let a = &self.channel_pressure_destination;
+        let b = &self.key_pressure_destination;
+
+        ControllerDestination {
+            pitch: channel * a.pitch + key_pressure * b.pitch,
+            filter_cutoff: channel * a.filter_cutoff + key_pressure * b.filter_cutoff,
+            amplitude: channel * a.amplitude + key_pressure * b.amplitude,
+            vibrato_depth: channel * a.vibrato_depth + key_pressure * b.vibrato_depth,
+        }
+    }
+
     pub(crate) fn get_reverb_send(&self) -> f32 {
         (1_f32 / 127_f32) * self.reverb_send as f32
     }
@@ -235,10 +756,77 @@ impl SynthChannel {
     }
 
     pub(crate) fn get_tune(&self) -> f32 {
-        self.coarse_tune as f32 + (1_f32 / 8192_f32) * (self.fine_tune - 8192) as f32
+        (self.transpose + self.coarse_tune as i32) as f32
+            + (1_f32 / 8192_f32) * (self.fine_tune as i32 - 8192) as f32
+    }
+
+    pub(crate) fn get_maximum_polyphony(&self) -> Option<usize> {
+        self.maximum_polyphony
+    }
+
+    pub(crate) fn get_tuning_program(&self) -> u8 {
+        self.tuning_program
+    }
+
+    pub(crate) fn get_tuning_bank(&self) -> u8 {
+        self.tuning_bank
+    }
+
+    pub(crate) fn get_tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    pub(crate) fn set_tuning(&mut self, value: &Tuning) {
+        self.tuning = *value;
+    }
+
+    /// Gets the pitch of a key in the tuning of the channel.
+    pub(crate) fn get_key_pitch(&self, key: u8) -> f32 {
+        self.tuning.get_pitch(key)
+    }
+
+    pub(crate) fn get_reserved_voices(&self) -> usize {
+        self.reserved_voices
+    }
+
+    /// Gets the offsets which the GS/XG NRPNs apply to the generators of a note.
+    pub(crate) fn get_generator_offsets(&self, key: u8) -> GeneratorOffsets {
+        self.nrpn_parameters
+            .get_generator_offsets(key, self.is_percussion_channel)
     }
 
     pub(crate) fn get_pitch_bend(&self) -> f32 {
         self.get_pitch_bend_range() * self.pitch_bend
     }
 }
+
+/// The state of the MIDI 2.0 per-note controllers of a key.
+#[derive(Clone, Copy)]
+pub(crate) struct PerNoteControllers {
+    /// The per-note pitch bend, from -1 to 1.
+    pub(crate) pitch_bend: f32,
+    /// The absolute pitch in semitones, which replaces the key if set.
+    pub(crate) pitch: Option<f32>,
+    /// The per-note modulation, from 0 to 1.
+    pub(crate) modulation: f32,
+    /// The per-note volume, from 0 to 1.
+    pub(crate) volume: f32,
+    /// The per-note expression, from 0 to 1.
+    pub(crate) expression: f32,
+    /// The per-note pan offset, from -1 to 1.
+    pub(crate) pan: f32,
+    /// The per-note brightness, from -1 to 1.
+    pub(crate) brightness: f32,
+}
+
+impl PerNoteControllers {
+    pub(crate) const DEFAULT: PerNoteControllers = PerNoteControllers {
+        pitch_bend: 0_f32,
+        pitch: None,
+        modulation: 0_f32,
+        volume: 1_f32,
+        expression: 1_f32,
+        pan: 0_f32,
+        brightness: 0_f32,
+    };
+}
@@ -1,107 +1,217 @@
 use core::f64::consts;
 
 use bevy_platform::prelude::*;
-pub(crate) struct Chorus {
+
+/// The parameters of the chorus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChorusParameters {
+    /// The center delay in seconds, up to [`ChorusParameters::MAXIMUM_DELAY`].
+    pub delay: f32,
+    /// The modulation depth of the delay in seconds, up to [`ChorusParameters::MAXIMUM_DEPTH`].
+    pub depth: f32,
+    /// The modulation rate in Hz.
+    pub rate: f32,
+    /// The amount of the output fed back to the input, from 0 to less than 1.
+    pub feedback: f32,
+    /// The output level, where 1 is the unity gain.
+    pub level: f32,
+}
+
+impl Default for ChorusParameters {
+    fn default() -> Self {
+        Self {
+            delay: 0.002,
+            depth: 0.0019,
+            rate: 0.4,
+            feedback: 0.0,
+            level: 1.0,
+        }
+    }
+}
+
+impl ChorusParameters {
+    /// The maximum delay in seconds.
+    pub const MAXIMUM_DELAY: f32 = 0.1;
+    /// The maximum modulation depth in seconds.
+    pub const MAXIMUM_DEPTH: f32 = 0.01;
+    /// The maximum modulation rate in Hz.
+    pub const MAXIMUM_RATE: f32 = 20.0;
+    /// The maximum feedback.
+    pub const MAXIMUM_FEEDBACK: f32 = 0.95;
+}
+
+/// The named chorus presets, which are the GS chorus macros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChorusPreset {
+    Chorus1,
+    Chorus2,
+    Chorus3,
+    Chorus4,
+    FeedbackChorus,
+    Flanger,
+    ShortDelay,
+    ShortDelayFeedback,
+}
+
+impl ChorusPreset {
+    /// Gets the chorus parameters of the preset.
+    pub fn get_parameters(&self) -> ChorusParameters {
+        let (delay, depth, rate, feedback) = match self {
+            ChorusPreset::Chorus1 => (0.008, 0.0015, 0.4, 0.0),
+            ChorusPreset::Chorus2 => (0.008, 0.002, 0.5, 0.05),
+            ChorusPreset::Chorus3 => (0.008, 0.003, 0.4, 0.1),
+            ChorusPreset::Chorus4 => (0.01, 0.0015, 0.5, 0.0),
+            ChorusPreset::FeedbackChorus => (0.006, 0.002, 0.4, 0.5),
+            ChorusPreset::Flanger => (0.0015, 0.0012, 0.25, 0.7),
+            ChorusPreset::ShortDelay => (0.05, 0.0, 0.0, 0.0),
+            ChorusPreset::ShortDelayFeedback => (0.05, 0.0, 0.0, 0.6),
+        };
+
+        ChorusParameters {
+            delay,
+            depth,
+            rate,
+            feedback,
+            level: 1.0,
+        }
+    }
+
+    /// Gets the preset of a GS chorus macro.
+    pub fn from_gs_macro(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ChorusPreset::Chorus1),
+            1 => Some(ChorusPreset::Chorus2),
+            2 => Some(ChorusPreset::Chorus3),
+            3 => Some(ChorusPreset::Chorus4),
+            4 => Some(ChorusPreset::FeedbackChorus),
+            5 => Some(ChorusPreset::Flanger),
+            6 => Some(ChorusPreset::ShortDelay),
+            7 => Some(ChorusPreset::ShortDelayFeedback),
+            _ => None,
+        }
+    }
+}
+
+/// A stereo chorus, which is the one behind the chorus send of the synthesizer.
+/// Only the modulated signal is output, so the dry signal has to be mixed by the caller.
+pub struct Chorus {
     buffer_l: Vec<f32>,
     buffer_r: Vec<f32>,
 
-    delay_table: Vec<f32>,
-
     buffer_index: usize,
 
-    delay_table_index_l: usize,
-    delay_table_index_r: usize,
+    sample_rate: f64,
+    parameters: ChorusParameters,
+
+    // The phase of the LFO, from 0 to 1. The right channel is a quarter of a cycle ahead.
+    phase: f64,
+    phase_step: f64,
 }
 
 impl Chorus {
-    pub(crate) fn new(sample_rate: i32, delay: f64, depth: f64, frequency: f64) -> Self {
-        let buffer_l = vec![0_f32; ((sample_rate as f64) * (delay + depth)) as usize + 2];
-        let buffer_r = vec![0_f32; ((sample_rate as f64) * (delay + depth)) as usize + 2];
-
-        let mut delay_table = vec![0_f32; ((sample_rate as f64) / frequency).round() as usize];
-        let delay_table_length = delay_table.len();
-        for (t, input) in delay_table.iter_mut().enumerate().take(delay_table_length) {
-            let phase = 2.0 * consts::PI * (t as f64) / (delay_table_length as f64);
-            *input = ((sample_rate as f64) * (delay + depth * phase.sin())) as f32;
-        }
-
-        let buffer_index: usize = 0;
+    /// Initializes a new chorus.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate of the signal.
+    /// * `parameters` - The parameters of the chorus.
+    pub fn new(sample_rate: i32, parameters: &ChorusParameters) -> Self {
+        // The buffers are sized for the largest delay, so that the parameters can change freely.
+        let buffer_length = ((sample_rate as f64)
+            * (ChorusParameters::MAXIMUM_DELAY + ChorusParameters::MAXIMUM_DEPTH) as f64)
+            as usize
+            + 2;
+
+        let mut chorus = Self {
+            buffer_l: vec![0_f32; buffer_length],
+            buffer_r: vec![0_f32; buffer_length],
+            buffer_index: 0,
+            sample_rate: sample_rate as f64,
+            parameters: ChorusParameters::default(),
+            phase: 0.0,
+            phase_step: 0.0,
+        };
+
+        chorus.set_parameters(parameters);
+
+        chorus
+    }
 
-        let delay_table_index_l: usize = 0;
-        let delay_table_index_r: usize = delay_table_length / 4;
+    /// Gets the parameters of the chorus.
+    pub fn get_parameters(&self) -> &ChorusParameters {
+        &self.parameters
+    }
 
-        Self {
-            buffer_l,
-            buffer_r,
-            delay_table,
-            buffer_index,
-            delay_table_index_l,
-            delay_table_index_r,
-        }
+    /// Sets the parameters of the chorus.
+    /// The values out of range are clamped, and the buffers are not reallocated.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the chorus.
+    pub fn set_parameters(&mut self, value: &ChorusParameters) {
+        self.parameters = ChorusParameters {
+            delay: value.delay.clamp(0_f32, ChorusParameters::MAXIMUM_DELAY),
+            depth: value.depth.clamp(0_f32, ChorusParameters::MAXIMUM_DEPTH),
+            rate: value.rate.clamp(0_f32, ChorusParameters::MAXIMUM_RATE),
+            feedback: value
+                .feedback
+                .clamp(0_f32, ChorusParameters::MAXIMUM_FEEDBACK),
+            level: value.level.max(0_f32),
+        };
+
+        self.phase_step = self.parameters.rate as f64 / self.sample_rate;
     }
 
-    pub(crate) fn process(
+    /// Processes a stereo signal.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_left` - The left channel of the input.
+    /// * `input_right` - The right channel of the input.
+    /// * `output_left` - The buffer to store the left channel of the chorus.
+    /// * `output_right` - The buffer to store the right channel of the chorus.
+    ///
+    /// # Remarks
+    ///
+    /// All the buffers must be the same length.
+    pub fn process(
         &mut self,
         input_left: &[f32],
         input_right: &[f32],
         output_left: &mut [f32],
         output_right: &mut [f32],
     ) {
+        if input_left.len() != output_left.len()
+            || input_right.len() != output_left.len()
+            || output_right.len() != output_left.len()
+        {
+            panic!("The input and output buffers must be the same length.");
+        }
+
         let buffer_length = self.buffer_l.len();
-        let delay_table_length = self.delay_table.len();
         let output_length = output_left.len();
 
+        let delay = self.parameters.delay as f64;
+        let depth = self.parameters.depth as f64;
+        let feedback = self.parameters.feedback;
+        let level = self.parameters.level;
+
         for t in 0..output_length {
-            {
-                let mut position =
-                    self.buffer_index as f64 - self.delay_table[self.delay_table_index_l] as f64;
-                if position < 0.0 {
-                    position += buffer_length as f64;
-                }
-
-                let index1 = position as usize;
-                let mut index2 = index1 + 1;
-                if index2 == buffer_length {
-                    index2 = 0;
-                }
-
-                let x1 = self.buffer_l[index1] as f64;
-                let x2 = self.buffer_l[index2] as f64;
-                let a = position - index1 as f64;
-                output_left[t] = (x1 + a * (x2 - x1)) as f32;
-
-                self.delay_table_index_l += 1;
-                if self.delay_table_index_l == delay_table_length {
-                    self.delay_table_index_l = 0;
-                }
-            }
+            let delay_l = self.get_delay(delay, depth, self.phase);
+            let delay_r = self.get_delay(delay, depth, self.phase + 0.25);
+
+            let left = Chorus::read(&self.buffer_l, self.buffer_index, delay_l);
+            let right = Chorus::read(&self.buffer_r, self.buffer_index, delay_r);
+            output_left[t] = level * left;
+            output_right[t] = level * right;
 
-            {
-                let mut position =
-                    self.buffer_index as f64 - self.delay_table[self.delay_table_index_r] as f64;
-                if position < 0.0 {
-                    position += buffer_length as f64;
-                }
-
-                let index1 = position as usize;
-                let mut index2 = index1 + 1;
-                if index2 == buffer_length {
-                    index2 = 0;
-                }
-
-                let x1 = self.buffer_r[index1] as f64;
-                let x2 = self.buffer_r[index2] as f64;
-                let a = position - index1 as f64;
-                output_right[t] = (x1 + a * (x2 - x1)) as f32;
-
-                self.delay_table_index_r += 1;
-                if self.delay_table_index_r == delay_table_length {
-                    self.delay_table_index_r = 0;
-                }
+            self.phase += self.phase_step;
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
             }
 
-            self.buffer_l[self.buffer_index] = input_left[t];
-            self.buffer_r[self.buffer_index] = input_right[t];
+            self.buffer_l[self.buffer_index] = input_left[t] + feedback * left;
+            self.buffer_r[self.buffer_index] = input_right[t] + feedback * right;
             self.buffer_index += 1;
             if self.buffer_index == buffer_length {
                 self.buffer_index = 0;
@@ -109,15 +219,36 @@ impl Chorus {
         }
     }
 
-    pub(crate) fn mute(&mut self) {
-        let buffer_length = self.buffer_l.len();
+    /// Gets the delay in samples at a phase of the LFO.
+    /// At least one sample of delay is kept, as the current sample has not been written yet.
+    fn get_delay(&self, delay: f64, depth: f64, phase: f64) -> f64 {
+        let seconds = delay + depth * (2.0 * consts::PI * phase).sin();
+        (self.sample_rate * seconds).max(1.0)
+    }
 
-        for t in 0..buffer_length {
-            self.buffer_l[t] = 0_f32;
+    fn read(buffer: &[f32], buffer_index: usize, delay: f64) -> f32 {
+        let buffer_length = buffer.len();
+
+        let mut position = buffer_index as f64 - delay;
+        if position < 0.0 {
+            position += buffer_length as f64;
         }
 
-        for t in 0..buffer_length {
-            self.buffer_r[t] = 0_f32;
+        let index1 = position as usize;
+        let mut index2 = index1 + 1;
+        if index2 == buffer_length {
+            index2 = 0;
         }
+
+        let x1 = buffer[index1] as f64;
+        let x2 = buffer[index2] as f64;
+        let a = position - index1 as f64;
+        (x1 + a * (x2 - x1)) as f32
+    }
+
+    /// Clears the sound in the delay buffers.
+    pub fn mute(&mut self) {
+        self.buffer_l.fill(0_f32);
+        self.buffer_r.fill(0_f32);
     }
 }
@@ -0,0 +1,47 @@
+/// Specifies how a pressure controller modulates the sound.
+///
+/// This follows the controller destination setting of GM2.
+/// Every amount is the change applied when the controller is at its maximum,
+/// and it is scaled linearly for smaller values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControllerDestination {
+    /// The pitch change in semitones.
+    pub pitch: f32,
+    /// The filter cutoff change in cents.
+    pub filter_cutoff: f32,
+    /// The amplitude change, where `0.0` leaves the volume as is and `1.0` doubles it.
+    pub amplitude: f32,
+    /// The vibrato depth added in cents.
+    pub vibrato_depth: f32,
+}
+
+impl ControllerDestination {
+    /// A destination which leaves the sound unchanged.
+    pub const NONE: ControllerDestination = ControllerDestination {
+        pitch: 0_f32,
+        filter_cutoff: 0_f32,
+        amplitude: 0_f32,
+        vibrato_depth: 0_f32,
+    };
+
+    /// The default destination of pressure controllers, which deepens the vibrato
+    /// and raises the volume by half at full pressure.
+    pub const PRESSURE_DEFAULT: ControllerDestination = ControllerDestination {
+        vibrato_depth: 50_f32,
+        amplitude: 0.5_f32,
+        ..ControllerDestination::NONE
+    };
+
+    /// The default destination of the channel pressure on MPE member channels,
+    /// which makes the pressure of each note control its loudness.
+    pub const MPE_PRESSURE_DEFAULT: ControllerDestination = ControllerDestination {
+        amplitude: 1_f32,
+        ..ControllerDestination::NONE
+    };
+}
+
+impl Default for ControllerDestination {
+    fn default() -> Self {
+        ControllerDestination::PRESSURE_DEFAULT
+    }
+}
@@ -1,5 +1,8 @@
 use core::error;
 use core::fmt;
+use std::io;
+
+use bevy_platform::prelude::*;
 
 /// Represents an error when initializing a synthesizer.
 #[derive(Debug)]
@@ -7,6 +10,9 @@ pub enum SynthesizerError {
     SampleRateOutOfRange(i32),
     BlockSizeOutOfRange(usize),
     MaximumPolyphonyOutOfRange(usize),
+    ChannelCountOutOfRange(usize),
+    OutputCountOutOfRange(usize),
+    ThreadCountOutOfRange(usize),
 }
 
 impl error::Error for SynthesizerError {}
@@ -28,6 +34,100 @@ impl fmt::Display for SynthesizerError {
                     "the maximum number of polyphony must be between 8 and 256, but was {value}",
                 )
             }
+            SynthesizerError::ChannelCountOutOfRange(value) => write!(
+                f,
+                "the number of channels must be a multiple of 16 between 16 and 256, but was {value}",
+            ),
+            SynthesizerError::OutputCountOutOfRange(value) => write!(
+                f,
+                "the number of outputs must be between 1 and the number of channels, but was {value}",
+            ),
+            SynthesizerError::ThreadCountOutOfRange(value) => write!(
+                f,
+                "the number of threads must be between 1 and 64, but was {value}",
+            ),
+        }
+    }
+}
+
+/// Represents an error when loading a tuning.
+#[derive(Debug)]
+pub enum TuningError {
+    InvalidScale,
+    InvalidPitch(String),
+    InvalidKeyboardMapping,
+    UnmappedReferenceKey(u8),
+}
+
+impl error::Error for TuningError {}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuningError::InvalidScale => write!(f, "the scale file is malformed"),
+            TuningError::InvalidPitch(value) => {
+                write!(f, "the pitch '{value}' in the scale file is invalid")
+            }
+            TuningError::InvalidKeyboardMapping => {
+                write!(f, "the keyboard mapping file is malformed")
+            }
+            TuningError::UnmappedReferenceKey(key) => {
+                write!(f, "the reference key {key} is not mapped to the scale")
+            }
+        }
+    }
+}
+
+/// Represents an error when loading an impulse response.
+#[derive(Debug)]
+pub enum ImpulseResponseError {
+    IoError(io::Error),
+    InvalidWaveFile,
+    UnsupportedFormat {
+        format_tag: u16,
+        bits_per_sample: u16,
+    },
+    InvalidSampleRate(i32),
+    InvalidLength {
+        left: usize,
+        right: usize,
+    },
+}
+
+impl error::Error for ImpulseResponseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ImpulseResponseError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImpulseResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImpulseResponseError::IoError(err) => fmt::Display::fmt(&err, f),
+            ImpulseResponseError::InvalidWaveFile => write!(f, "the WAV file is malformed"),
+            ImpulseResponseError::UnsupportedFormat {
+                format_tag,
+                bits_per_sample,
+            } => write!(
+                f,
+                "the WAV format {format_tag} with {bits_per_sample} bits per sample is not supported"
+            ),
+            ImpulseResponseError::InvalidSampleRate(value) => {
+                write!(f, "the sample rate must be positive, but was {value}")
+            }
+            ImpulseResponseError::InvalidLength { left, right } => write!(
+                f,
+                "the channels must have the same non-zero length, but were {left} and {right}"
+            ),
         }
     }
 }
+
+impl From<io::Error> for ImpulseResponseError {
+    fn from(err: io::Error) -> Self {
+        ImpulseResponseError::IoError(err)
+    }
+}
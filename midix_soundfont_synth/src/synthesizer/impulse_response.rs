@@ -0,0 +1,268 @@
+use std::io;
+
+use bevy_platform::prelude::*;
+
+use crate::prelude::*;
+
+/// A stereo impulse response for the convolution reverb.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpulseResponse {
+    sample_rate: i32,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl ImpulseResponse {
+    const FORMAT_PCM: u16 = 1;
+    const FORMAT_IEEE_FLOAT: u16 = 3;
+    const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+    /// Creates an impulse response from its samples.
+    /// It is resampled if its sample rate differs from the one of the synthesizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate of the impulse response.
+    /// * `left` - The samples of the left channel.
+    /// * `right` - The samples of the right channel.
+    pub fn new(
+        sample_rate: i32,
+        left: Vec<f32>,
+        right: Vec<f32>,
+    ) -> Result<Self, ImpulseResponseError> {
+        if sample_rate <= 0 {
+            return Err(ImpulseResponseError::InvalidSampleRate(sample_rate));
+        }
+
+        if left.is_empty() || left.len() != right.len() {
+            return Err(ImpulseResponseError::InvalidLength {
+                left: left.len(),
+                right: right.len(),
+            });
+        }
+
+        Ok(Self {
+            sample_rate,
+            left,
+            right,
+        })
+    }
+
+    /// Loads an impulse response from a WAV file.
+    /// Mono files are used for both channels, and only the first two channels are used otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The data stream used to load the WAV file.
+    pub fn from_wav<R: Read + ?Sized>(reader: &mut R) -> Result<Self, ImpulseResponseError> {
+        let chunk_id = BinaryReader::read_four_cc(reader)?;
+        if chunk_id != b"RIFF" {
+            return Err(ImpulseResponseError::InvalidWaveFile);
+        }
+
+        let _size = BinaryReader::read_u32(reader)?;
+
+        let form_type = BinaryReader::read_four_cc(reader)?;
+        if form_type != b"WAVE" {
+            return Err(ImpulseResponseError::InvalidWaveFile);
+        }
+
+        let mut format: Option<WaveFormat> = None;
+        loop {
+            let id = BinaryReader::read_four_cc(reader)?;
+            let size = BinaryReader::read_u32(reader)? as usize;
+
+            if id == b"fmt " {
+                let data = ImpulseResponse::read_chunk(reader, size)?;
+                format = Some(WaveFormat::parse(&data)?);
+            } else if id == b"data" {
+                let format = format.ok_or(ImpulseResponseError::InvalidWaveFile)?;
+                let data = ImpulseResponse::read_chunk(reader, size)?;
+                return format.decode(&data);
+            } else {
+                BinaryReader::discard_data(reader, size)?;
+            }
+
+            // Chunks are padded to an even size.
+            if !size.is_multiple_of(2) {
+                BinaryReader::discard_data(reader, 1)?;
+            }
+        }
+    }
+
+    /// Reads the data of a chunk.
+    /// The buffer grows as the data is read, so that a corrupted size cannot allocate more than the file holds.
+    fn read_chunk<R: Read + ?Sized>(
+        reader: &mut R,
+        size: usize,
+    ) -> Result<Vec<u8>, ImpulseResponseError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.take(size as u64).read_to_end(&mut data)?;
+        if data.len() != size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(data)
+    }
+
+    /// Gets the sample rate of the impulse response.
+    pub fn get_sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    /// Gets the length of the impulse response in samples.
+    pub fn get_length(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Gets the samples of the left channel.
+    pub fn get_left(&self) -> &[f32] {
+        &self.left[..]
+    }
+
+    /// Gets the samples of the right channel.
+    pub fn get_right(&self) -> &[f32] {
+        &self.right[..]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct WaveFormat {
+    format_tag: u16,
+    channel_count: usize,
+    sample_rate: i32,
+    block_align: usize,
+    bits_per_sample: u16,
+}
+
+impl WaveFormat {
+    fn parse(data: &[u8]) -> Result<Self, ImpulseResponseError> {
+        if data.len() < 16 {
+            return Err(ImpulseResponseError::InvalidWaveFile);
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+
+        // The extensible format stores the actual format at the start of the sub-format GUID.
+        let mut format_tag = read_u16(0);
+        if format_tag == ImpulseResponse::FORMAT_EXTENSIBLE && data.len() >= 26 {
+            format_tag = read_u16(24);
+        }
+
+        let format = Self {
+            format_tag,
+            channel_count: read_u16(2) as usize,
+            sample_rate: read_u32(4) as i32,
+            block_align: read_u16(12) as usize,
+            bits_per_sample: read_u16(14),
+        };
+
+        let bytes_per_sample = (format.bits_per_sample as usize).div_ceil(8);
+        if format.channel_count == 0 || format.block_align < format.channel_count * bytes_per_sample
+        {
+            return Err(ImpulseResponseError::InvalidWaveFile);
+        }
+
+        match (format.format_tag, format.bits_per_sample) {
+            (ImpulseResponse::FORMAT_PCM, 8 | 16 | 24 | 32)
+            | (ImpulseResponse::FORMAT_IEEE_FLOAT, 32 | 64) => Ok(format),
+            (format_tag, bits_per_sample) => Err(ImpulseResponseError::UnsupportedFormat {
+                format_tag,
+                bits_per_sample,
+            }),
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<ImpulseResponse, ImpulseResponseError> {
+        let bytes_per_sample = self.bits_per_sample as usize / 8;
+        let right_offset = if self.channel_count > 1 {
+            bytes_per_sample
+        } else {
+            0
+        };
+
+        let frames = data.chunks_exact(self.block_align);
+        let mut left = Vec::with_capacity(frames.len());
+        let mut right = Vec::with_capacity(frames.len());
+        for frame in frames {
+            left.push(self.decode_sample(&frame[..bytes_per_sample]));
+            right.push(self.decode_sample(&frame[right_offset..right_offset + bytes_per_sample]));
+        }
+
+        ImpulseResponse::new(self.sample_rate, left, right)
+    }
+
+    fn decode_sample(&self, bytes: &[u8]) -> f32 {
+        match (self.format_tag, bytes) {
+            (ImpulseResponse::FORMAT_PCM, &[a]) => (a as f32 - 128_f32) / 128_f32,
+            (ImpulseResponse::FORMAT_PCM, &[a, b]) => i16::from_le_bytes([a, b]) as f32 / 32768_f32,
+            (ImpulseResponse::FORMAT_PCM, &[a, b, c]) => {
+                (i32::from_le_bytes([0, a, b, c]) >> 8) as f32 / 8388608_f32
+            }
+            (ImpulseResponse::FORMAT_PCM, &[a, b, c, d]) => {
+                i32::from_le_bytes([a, b, c, d]) as f32 / 2147483648_f32
+            }
+            (_, &[a, b, c, d]) => f32::from_le_bytes([a, b, c, d]),
+            (_, &[a, b, c, d, e, f, g, h]) => f64::from_le_bytes([a, b, c, d, e, f, g, h]) as f32,
+            _ => 0_f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a 16-bit stereo WAV file, whose data chunk has the given size in its header.
+    fn create_wav(samples: &[i16], data_size: u32) -> Vec<u8> {
+        let mut wav = b"RIFF".to_vec();
+        wav.extend(0_u32.to_le_bytes());
+        wav.extend(b"WAVE");
+
+        wav.extend(b"fmt ");
+        wav.extend(16_u32.to_le_bytes());
+        for value in [ImpulseResponse::FORMAT_PCM, 2] {
+            wav.extend(value.to_le_bytes());
+        }
+        for value in [48000_u32, 48000 * 4] {
+            wav.extend(value.to_le_bytes());
+        }
+        for value in [4_u16, 16] {
+            wav.extend(value.to_le_bytes());
+        }
+
+        wav.extend(b"data");
+        wav.extend(data_size.to_le_bytes());
+        for sample in samples {
+            wav.extend(sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn reads_the_channels() {
+        let wav = create_wav(&[16384, -16384, 0, 8192], 8);
+        let impulse_response = ImpulseResponse::from_wav(&mut &wav[..]).unwrap();
+
+        assert_eq!(impulse_response.get_sample_rate(), 48000);
+        assert_eq!(impulse_response.get_left(), &[0.5_f32, 0_f32]);
+        assert_eq!(impulse_response.get_right(), &[-0.5_f32, 0.25_f32]);
+    }
+
+    #[test]
+    fn rejects_a_chunk_larger_than_the_file() {
+        let wav = create_wav(&[16384, -16384], u32::MAX);
+
+        assert!(matches!(
+            ImpulseResponse::from_wav(&mut &wav[..]),
+            Err(ImpulseResponseError::IoError(error)) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+}
@@ -0,0 +1,765 @@
+use core::f32::consts;
+
+use bevy_platform::prelude::*;
+
+/// The shape of an equalizer band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqualizerBandType {
+    LowShelf,
+    Peak,
+    HighShelf,
+    LowPass,
+    HighPass,
+}
+
+/// A band of the parametric equalizer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqualizerBand {
+    /// The shape of the band.
+    pub band_type: EqualizerBandType,
+    /// The center or corner frequency in Hz.
+    pub frequency: f32,
+    /// The gain in dB. This has no effect on the low-pass and high-pass bands.
+    pub gain: f32,
+    /// The quality factor, which sets the bandwidth.
+    pub q: f32,
+}
+
+/// The parameters of the master equalizer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqualizerParameters {
+    /// The value indicating whether the equalizer is enabled.
+    pub enabled: bool,
+    /// The bands, which are applied in order.
+    pub bands: [EqualizerBand; EqualizerParameters::BAND_COUNT],
+}
+
+impl Default for EqualizerParameters {
+    fn default() -> Self {
+        let band = |band_type, frequency| EqualizerBand {
+            band_type,
+            frequency,
+            gain: 0.0,
+            q: consts::FRAC_1_SQRT_2,
+        };
+
+        Self {
+            enabled: false,
+            bands: [
+                band(EqualizerBandType::LowShelf, 100.0),
+                band(EqualizerBandType::Peak, 500.0),
+                band(EqualizerBandType::Peak, 2000.0),
+                band(EqualizerBandType::HighShelf, 8000.0),
+            ],
+        }
+    }
+}
+
+impl EqualizerParameters {
+    /// The number of bands.
+    pub const BAND_COUNT: usize = 4;
+}
+
+/// The parameters of the master compressor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressorParameters {
+    /// The value indicating whether the compressor is enabled.
+    pub enabled: bool,
+    /// The level above which the signal is compressed, in dBFS.
+    pub threshold: f32,
+    /// The compression ratio, which is 1 or more.
+    pub ratio: f32,
+    /// The width of the soft knee in dB.
+    pub knee: f32,
+    /// The attack time in seconds.
+    pub attack: f32,
+    /// The release time in seconds.
+    pub release: f32,
+    /// The gain applied after the compression, in dB.
+    pub makeup_gain: f32,
+}
+
+impl Default for CompressorParameters {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: -12.0,
+            ratio: 2.0,
+            knee: 6.0,
+            attack: 0.01,
+            release: 0.2,
+            makeup_gain: 0.0,
+        }
+    }
+}
+
+/// The parameters of the master limiter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimiterParameters {
+    /// The value indicating whether the limiter is enabled.
+    /// The limiter delays the output by its look-ahead time.
+    pub enabled: bool,
+    /// The maximum true-peak level of the output, in dBFS.
+    pub ceiling: f32,
+    /// The look-ahead time in seconds, up to [`LimiterParameters::MAXIMUM_LOOK_AHEAD`].
+    pub look_ahead: f32,
+    /// The release time in seconds.
+    pub release: f32,
+}
+
+impl Default for LimiterParameters {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ceiling: -1.0,
+            look_ahead: 0.005,
+            release: 0.1,
+        }
+    }
+}
+
+impl LimiterParameters {
+    /// The maximum look-ahead time in seconds.
+    pub const MAXIMUM_LOOK_AHEAD: f32 = 0.02;
+}
+
+/// The processing on the mixed output: an equalizer, a compressor and a limiter, in this order.
+pub(crate) struct MasterChain {
+    equalizer: Equalizer,
+    compressor: Compressor,
+    limiter: Limiter,
+}
+
+impl MasterChain {
+    pub(crate) fn new(sample_rate: i32) -> Self {
+        Self {
+            equalizer: Equalizer::new(sample_rate as f32),
+            compressor: Compressor::new(sample_rate as f32),
+            limiter: Limiter::new(sample_rate as f32),
+        }
+    }
+
+    pub(crate) fn get_equalizer_parameters(&self) -> &EqualizerParameters {
+        &self.equalizer.parameters
+    }
+
+    pub(crate) fn set_equalizer_parameters(&mut self, value: &EqualizerParameters) {
+        self.equalizer.set_parameters(value);
+    }
+
+    pub(crate) fn get_compressor_parameters(&self) -> &CompressorParameters {
+        &self.compressor.parameters
+    }
+
+    pub(crate) fn set_compressor_parameters(&mut self, value: &CompressorParameters) {
+        self.compressor.set_parameters(value);
+    }
+
+    pub(crate) fn get_limiter_parameters(&self) -> &LimiterParameters {
+        &self.limiter.parameters
+    }
+
+    pub(crate) fn set_limiter_parameters(&mut self, value: &LimiterParameters) {
+        self.limiter.set_parameters(value);
+    }
+
+    pub(crate) fn get_limiter_gain_reduction(&self) -> f32 {
+        self.limiter.gain_reduction
+    }
+
+    pub(crate) fn mute(&mut self) {
+        self.equalizer.mute();
+        self.compressor.mute();
+        self.limiter.mute();
+    }
+
+    pub(crate) fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if self.equalizer.parameters.enabled {
+            self.equalizer.process(left, right);
+        }
+
+        if self.compressor.parameters.enabled {
+            self.compressor.process(left, right);
+        }
+
+        if self.limiter.parameters.enabled {
+            self.limiter.process(left, right);
+        }
+    }
+}
+
+struct Equalizer {
+    sample_rate: f32,
+    parameters: EqualizerParameters,
+    filters: [StereoBiQuad; EqualizerParameters::BAND_COUNT],
+}
+
+impl Equalizer {
+    fn new(sample_rate: f32) -> Self {
+        let mut equalizer = Self {
+            sample_rate,
+            parameters: EqualizerParameters::default(),
+            filters: [StereoBiQuad::default(); EqualizerParameters::BAND_COUNT],
+        };
+
+        equalizer.set_parameters(&EqualizerParameters::default());
+
+        equalizer
+    }
+
+    /// Applies the parameters, keeping the states of the filters.
+    fn set_parameters(&mut self, value: &EqualizerParameters) {
+        // The filter states hold an old signal while the equalizer is off.
+        if !self.parameters.enabled && value.enabled {
+            self.mute();
+        }
+
+        self.parameters = *value;
+        for (band, filter) in self
+            .parameters
+            .bands
+            .iter_mut()
+            .zip(self.filters.iter_mut())
+        {
+            band.frequency = band.frequency.clamp(10_f32, 0.45_f32 * self.sample_rate);
+            band.q = band.q.max(0.1_f32);
+            filter.set_band(band, self.sample_rate);
+        }
+    }
+
+    fn mute(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.mute();
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for filter in self.filters.iter_mut() {
+            filter.process(left, right);
+        }
+    }
+}
+
+/// A biquad filter from the Audio EQ Cookbook, with a state for each channel.
+#[derive(Clone, Copy, Default)]
+struct StereoBiQuad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+
+    // The states of the transposed direct form II.
+    left: [f32; 2],
+    right: [f32; 2],
+}
+
+impl StereoBiQuad {
+    fn set_band(&mut self, band: &EqualizerBand, sample_rate: f32) {
+        let a = 10_f32.powf(band.gain / 40_f32);
+        let w = 2_f32 * consts::PI * band.frequency / sample_rate;
+        let cosw = w.cos();
+        let alpha = w.sin() / (2_f32 * band.q);
+        let beta = 2_f32 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.band_type {
+            EqualizerBandType::LowShelf => (
+                a * ((a + 1_f32) - (a - 1_f32) * cosw + beta),
+                2_f32 * a * ((a - 1_f32) - (a + 1_f32) * cosw),
+                a * ((a + 1_f32) - (a - 1_f32) * cosw - beta),
+                (a + 1_f32) + (a - 1_f32) * cosw + beta,
+                -2_f32 * ((a - 1_f32) + (a + 1_f32) * cosw),
+                (a + 1_f32) + (a - 1_f32) * cosw - beta,
+            ),
+            EqualizerBandType::Peak => (
+                1_f32 + alpha * a,
+                -2_f32 * cosw,
+                1_f32 - alpha * a,
+                1_f32 + alpha / a,
+                -2_f32 * cosw,
+                1_f32 - alpha / a,
+            ),
+            EqualizerBandType::HighShelf => (
+                a * ((a + 1_f32) + (a - 1_f32) * cosw + beta),
+                -2_f32 * a * ((a - 1_f32) + (a + 1_f32) * cosw),
+                a * ((a + 1_f32) + (a - 1_f32) * cosw - beta),
+                (a + 1_f32) - (a - 1_f32) * cosw + beta,
+                2_f32 * ((a - 1_f32) - (a + 1_f32) * cosw),
+                (a + 1_f32) - (a - 1_f32) * cosw - beta,
+            ),
+            EqualizerBandType::LowPass => (
+                (1_f32 - cosw) / 2_f32,
+                1_f32 - cosw,
+                (1_f32 - cosw) / 2_f32,
+                1_f32 + alpha,
+                -2_f32 * cosw,
+                1_f32 - alpha,
+            ),
+            EqualizerBandType::HighPass => (
+                (1_f32 + cosw) / 2_f32,
+                -(1_f32 + cosw),
+                (1_f32 + cosw) / 2_f32,
+                1_f32 + alpha,
+                -2_f32 * cosw,
+                1_f32 - alpha,
+            ),
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    fn mute(&mut self) {
+        self.left = [0_f32; 2];
+        self.right = [0_f32; 2];
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let coefficients = (self.b0, self.b1, self.b2, self.a1, self.a2);
+        StereoBiQuad::process_channel(coefficients, &mut self.left, left);
+        StereoBiQuad::process_channel(coefficients, &mut self.right, right);
+    }
+
+    fn process_channel(
+        (b0, b1, b2, a1, a2): (f32, f32, f32, f32, f32),
+        state: &mut [f32; 2],
+        block: &mut [f32],
+    ) {
+        for sample in block.iter_mut() {
+            let input = *sample;
+            let output = b0 * input + state[0];
+            state[0] = b1 * input - a1 * output + state[1];
+            state[1] = b2 * input - a2 * output;
+            *sample = output;
+        }
+    }
+}
+
+struct Compressor {
+    sample_rate: f32,
+    parameters: CompressorParameters,
+
+    attack_coefficient: f32,
+    release_coefficient: f32,
+
+    // The smoothed gain reduction in dB, which is zero or negative.
+    envelope: f32,
+}
+
+impl Compressor {
+    fn new(sample_rate: f32) -> Self {
+        let mut compressor = Self {
+            sample_rate,
+            parameters: CompressorParameters::default(),
+            attack_coefficient: 0_f32,
+            release_coefficient: 0_f32,
+            envelope: 0_f32,
+        };
+
+        compressor.set_parameters(&CompressorParameters::default());
+
+        compressor
+    }
+
+    fn set_parameters(&mut self, value: &CompressorParameters) {
+        self.parameters = CompressorParameters {
+            enabled: value.enabled,
+            threshold: value.threshold.min(0_f32),
+            ratio: value.ratio.max(1_f32),
+            knee: value.knee.max(0_f32),
+            attack: value.attack.max(0_f32),
+            release: value.release.max(0_f32),
+            makeup_gain: value.makeup_gain,
+        };
+
+        self.attack_coefficient =
+            Compressor::get_coefficient(self.parameters.attack, self.sample_rate);
+        self.release_coefficient =
+            Compressor::get_coefficient(self.parameters.release, self.sample_rate);
+    }
+
+    fn get_coefficient(time: f32, sample_rate: f32) -> f32 {
+        if time > 0_f32 {
+            (-1_f32 / (time * sample_rate)).exp()
+        } else {
+            0_f32
+        }
+    }
+
+    fn mute(&mut self) {
+        self.envelope = 0_f32;
+    }
+
+    /// Gets the gain reduction in dB for an input level in dB, with a soft knee.
+    fn get_gain_reduction(&self, level: f32) -> f32 {
+        let slope = 1_f32 / self.parameters.ratio - 1_f32;
+        let over = level - self.parameters.threshold;
+        let knee = self.parameters.knee;
+
+        if 2_f32 * over <= -knee {
+            0_f32
+        } else if 2_f32 * over.abs() < knee {
+            slope * (over + knee / 2_f32).powi(2) / (2_f32 * knee)
+        } else {
+            slope * over
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let makeup_gain = self.parameters.makeup_gain;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            // The channels are linked, so that the stereo image does not move.
+            let peak = l.abs().max(r.abs());
+            let level = 20_f32 * (peak + 1.0E-9_f32).log10();
+            let gain_reduction = self.get_gain_reduction(level);
+
+            let coefficient = if gain_reduction < self.envelope {
+                self.attack_coefficient
+            } else {
+                self.release_coefficient
+            };
+            self.envelope = gain_reduction + coefficient * (self.envelope - gain_reduction);
+
+            let gain = 10_f32.powf((self.envelope + makeup_gain) / 20_f32);
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+}
+
+/// A look-ahead limiter, which keeps the true peak under the ceiling.
+///
+/// The gain needed by each sample is held for the look-ahead time and then averaged over it,
+/// so that the gain has fully come down when the delayed peak is output.
+struct Limiter {
+    sample_rate: f32,
+    parameters: LimiterParameters,
+
+    ceiling: f32,
+    release_coefficient: f32,
+    look_ahead: usize,
+
+    // The last four input samples of each channel, for the true-peak detection.
+    history_left: [f32; 4],
+    history_right: [f32; 4],
+
+    release_gain: f32,
+
+    // A monotonic queue of the held gains and their times, for the sliding minimum.
+    minimum_gains: Vec<f32>,
+    minimum_times: Vec<usize>,
+    minimum_head: usize,
+    minimum_length: usize,
+    time: usize,
+
+    average_buffer: Vec<f32>,
+    average_sum: f64,
+
+    delay_left: Vec<f32>,
+    delay_right: Vec<f32>,
+    buffer_index: usize,
+
+    // The largest gain reduction in the last block, in dB.
+    gain_reduction: f32,
+}
+
+impl Limiter {
+    fn new(sample_rate: f32) -> Self {
+        let capacity = (sample_rate * LimiterParameters::MAXIMUM_LOOK_AHEAD) as usize + 1;
+
+        let mut limiter = Self {
+            sample_rate,
+            parameters: LimiterParameters::default(),
+            ceiling: 0_f32,
+            release_coefficient: 0_f32,
+            look_ahead: 1,
+            history_left: [0_f32; 4],
+            history_right: [0_f32; 4],
+            release_gain: 1_f32,
+            minimum_gains: vec![0_f32; capacity],
+            minimum_times: vec![0; capacity],
+            minimum_head: 0,
+            minimum_length: 0,
+            time: 0,
+            average_buffer: vec![0_f32; capacity],
+            average_sum: 0_f64,
+            delay_left: vec![0_f32; capacity],
+            delay_right: vec![0_f32; capacity],
+            buffer_index: 0,
+            gain_reduction: 0_f32,
+        };
+
+        limiter.set_parameters(&LimiterParameters::default());
+
+        limiter
+    }
+
+    /// Applies the parameters. Changing the look-ahead time clears the limiter.
+    fn set_parameters(&mut self, value: &LimiterParameters) {
+        let previous = self.parameters;
+        self.parameters = LimiterParameters {
+            enabled: value.enabled,
+            ceiling: value.ceiling.min(0_f32),
+            look_ahead: value
+                .look_ahead
+                .clamp(0_f32, LimiterParameters::MAXIMUM_LOOK_AHEAD),
+            release: value.release.max(0_f32),
+        };
+
+        self.ceiling = 10_f32.powf(self.parameters.ceiling / 20_f32);
+        self.release_coefficient =
+            Compressor::get_coefficient(self.parameters.release, self.sample_rate);
+
+        let look_ahead = ((self.sample_rate * self.parameters.look_ahead) as usize)
+            .clamp(1, self.delay_left.len());
+        // The buffers hold an old signal while the limiter is off.
+        if look_ahead != self.look_ahead || (!previous.enabled && self.parameters.enabled) {
+            self.look_ahead = look_ahead;
+            self.mute();
+        }
+    }
+
+    fn mute(&mut self) {
+        self.history_left = [0_f32; 4];
+        self.history_right = [0_f32; 4];
+        self.release_gain = 1_f32;
+        self.minimum_head = 0;
+        self.minimum_length = 0;
+        self.time = 0;
+        // The average starts from the unity gain.
+        self.average_buffer.fill(1_f32);
+        self.average_sum = self.look_ahead as f64;
+        self.delay_left.fill(0_f32);
+        self.delay_right.fill(0_f32);
+        self.buffer_index = 0;
+        self.gain_reduction = 0_f32;
+    }
+
+    /// Estimates the true peak between the second and the third samples with 4x oversampling,
+    /// using Catmull-Rom interpolation.
+    fn get_true_peak(history: &[f32; 4]) -> f32 {
+        let [x0, x1, x2, x3] = *history;
+        let mut peak = x2.abs();
+        for t in [0.25_f32, 0.5_f32, 0.75_f32] {
+            let value = x1
+                + 0.5_f32
+                    * t
+                    * (x2 - x0
+                        + t * (2_f32 * x0 - 5_f32 * x1 + 4_f32 * x2 - x3
+                            + t * (3_f32 * (x1 - x2) + x3 - x0)));
+            peak = peak.max(value.abs());
+        }
+        peak
+    }
+
+    fn push_minimum(&mut self, gain: f32) {
+        let capacity = self.minimum_gains.len();
+
+        while self.minimum_length > 0 {
+            let back = (self.minimum_head + self.minimum_length - 1) % capacity;
+            if self.minimum_gains[back] < gain {
+                break;
+            }
+            self.minimum_length -= 1;
+        }
+
+        let back = (self.minimum_head + self.minimum_length) % capacity;
+        self.minimum_gains[back] = gain;
+        self.minimum_times[back] = self.time;
+        self.minimum_length += 1;
+
+        while self.minimum_times[self.minimum_head] + self.look_ahead <= self.time {
+            self.minimum_head = (self.minimum_head + 1) % capacity;
+            self.minimum_length -= 1;
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let look_ahead = self.look_ahead;
+        let mut minimum_gain = 1_f32;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.history_left.rotate_left(1);
+            self.history_left[3] = *l;
+            self.history_right.rotate_left(1);
+            self.history_right[3] = *r;
+
+            let peak = Limiter::get_true_peak(&self.history_left)
+                .max(Limiter::get_true_peak(&self.history_right));
+            let target = if peak > self.ceiling {
+                self.ceiling / peak
+            } else {
+                1_f32
+            };
+
+            self.release_gain =
+                target.min(1_f32 - self.release_coefficient * (1_f32 - self.release_gain));
+            self.push_minimum(self.release_gain);
+            let held_gain = self.minimum_gains[self.minimum_head];
+
+            let index = self.buffer_index;
+            self.average_sum += held_gain as f64 - self.average_buffer[index] as f64;
+            self.average_buffer[index] = held_gain;
+            let gain = (self.average_sum / look_ahead as f64).min(1_f64) as f32;
+
+            let delayed_left = self.delay_left[index];
+            let delayed_right = self.delay_right[index];
+            self.delay_left[index] = *l;
+            self.delay_right[index] = *r;
+            *l = gain * delayed_left;
+            *r = gain * delayed_right;
+
+            minimum_gain = minimum_gain.min(gain);
+
+            self.buffer_index += 1;
+            if self.buffer_index == look_ahead {
+                self.buffer_index = 0;
+            }
+            self.time += 1;
+        }
+
+        self.gain_reduction = 20_f32 * minimum_gain.recip().log10();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: i32 = 48000;
+
+    /// A sine at 997 Hz, which does not line up with the samples, with a burst on top.
+    fn create_hot_signal(length: usize, amplitude: f32) -> (Vec<f32>, Vec<f32>) {
+        let left = (0..length)
+            .map(|i| {
+                let phase = 2_f32 * consts::PI * 997_f32 * i as f32 / SAMPLE_RATE as f32;
+                let burst = if i % 4800 < 10 { 2_f32 } else { 1_f32 };
+                burst * amplitude * phase.sin()
+            })
+            .collect::<Vec<f32>>();
+        let right = left.iter().map(|x| -0.5_f32 * x).collect();
+        (left, right)
+    }
+
+    fn process(chain: &mut MasterChain, left: &mut [f32], right: &mut [f32]) {
+        for (left, right) in left.chunks_mut(64).zip(right.chunks_mut(64)) {
+            chain.process(left, right);
+        }
+    }
+
+    #[test]
+    fn limiter_keeps_the_output_under_the_ceiling() {
+        for (ceiling, look_ahead) in [(-1_f32, 0.005_f32), (-6_f32, 0.001_f32), (0_f32, 0_f32)] {
+            let mut chain = MasterChain::new(SAMPLE_RATE);
+            chain.set_limiter_parameters(&LimiterParameters {
+                enabled: true,
+                ceiling,
+                look_ahead,
+                ..LimiterParameters::default()
+            });
+
+            let (mut left, mut right) = create_hot_signal(SAMPLE_RATE as usize, 4_f32);
+            process(&mut chain, &mut left, &mut right);
+
+            let limit = 10_f32.powf(ceiling / 20_f32) * (1_f32 + 1.0E-6_f32);
+            let peak = left
+                .iter()
+                .chain(&right)
+                .fold(0_f32, |peak, x| peak.max(x.abs()));
+            assert!(peak <= limit, "ceiling {ceiling} dB: peak {peak}");
+            // The signal is limited, not muted.
+            assert!(peak > 0.5_f32 * limit);
+        }
+    }
+
+    #[test]
+    fn limiter_delays_the_output_by_the_look_ahead() {
+        let mut chain = MasterChain::new(SAMPLE_RATE);
+        chain.set_limiter_parameters(&LimiterParameters {
+            enabled: true,
+            look_ahead: 0.005,
+            ..LimiterParameters::default()
+        });
+
+        let mut left = vec![0_f32; 1024];
+        let mut right = vec![0_f32; 1024];
+        left[10] = 0.5_f32;
+        process(&mut chain, &mut left, &mut right);
+
+        assert_eq!(left[10 + 240], 0.5_f32);
+        assert_eq!(left.iter().filter(|x| **x != 0_f32).count(), 1);
+    }
+
+    #[test]
+    fn limiter_reports_the_gain_reduction() {
+        let mut chain = MasterChain::new(SAMPLE_RATE);
+        chain.set_limiter_parameters(&LimiterParameters {
+            enabled: true,
+            ceiling: -1_f32,
+            ..LimiterParameters::default()
+        });
+        assert_eq!(chain.get_limiter_gain_reduction(), 0_f32);
+
+        // A steady sine, so that the gain settles.
+        let amplitude = 2_f32;
+        let mut left: Vec<f32> = (0..SAMPLE_RATE as usize)
+            .map(|i| amplitude * (2_f32 * consts::PI * 1000_f32 * i as f32 / 48000_f32).sin())
+            .collect();
+        let mut right = left.clone();
+        process(&mut chain, &mut left, &mut right);
+
+        let last = &left[left.len() - 64..];
+        let peak = last.iter().fold(0_f32, |peak, x| peak.max(x.abs()));
+        let applied = 20_f32 * (amplitude / peak).log10();
+        let reported = chain.get_limiter_gain_reduction();
+        assert!(
+            (reported - applied).abs() < 0.1_f32,
+            "reported {reported} dB, applied {applied} dB"
+        );
+        assert!((reported - 20_f32 * (amplitude / 10_f32.powf(-0.05_f32)).log10()).abs() < 0.1);
+    }
+
+    #[test]
+    fn disabled_stages_do_not_change_the_signal() {
+        let mut equalizer = EqualizerParameters::default();
+        equalizer.bands[1].gain = 6_f32;
+        let compressor = CompressorParameters {
+            threshold: -30_f32,
+            makeup_gain: 6_f32,
+            ..CompressorParameters::default()
+        };
+        let limiter = LimiterParameters {
+            ceiling: -12_f32,
+            ..LimiterParameters::default()
+        };
+
+        // The stages are disabled after they have processed a signal.
+        let mut chain = MasterChain::new(SAMPLE_RATE);
+        for enabled in [true, false] {
+            chain.set_equalizer_parameters(&EqualizerParameters {
+                enabled,
+                ..equalizer
+            });
+            chain.set_compressor_parameters(&CompressorParameters {
+                enabled,
+                ..compressor
+            });
+            chain.set_limiter_parameters(&LimiterParameters { enabled, ..limiter });
+
+            let (input_left, input_right) = create_hot_signal(4800, 1_f32);
+            let (mut left, mut right) = (input_left.clone(), input_right.clone());
+            process(&mut chain, &mut left, &mut right);
+
+            let unchanged = left
+                .iter()
+                .chain(&right)
+                .zip(input_left.iter().chain(&input_right))
+                .all(|(x, y)| x.to_bits() == y.to_bits());
+            assert_eq!(unchanged, !enabled);
+        }
+    }
+}
@@ -2,13 +2,28 @@ pub mod voice;
 
 mod chorus;
 use core::cmp;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use chorus::*;
+pub use chorus::*;
 
 mod reverb;
 use midix::prelude::ChannelVoiceMessage;
-use reverb::*;
+pub use reverb::*;
+
+mod freeverb;
+use freeverb::*;
+
+mod convolution;
+use convolution::*;
+
+mod impulse_response;
+pub use impulse_response::*;
+
+mod fdn;
+use fdn::*;
+
+mod master;
+pub use master::*;
 
 mod settings;
 pub use settings::*;
@@ -22,9 +37,32 @@ use array_math::*;
 mod loop_mode;
 pub use loop_mode::*;
 
+mod controller_destination;
+pub use controller_destination::*;
+
+mod mpe;
+pub use mpe::*;
+
+mod ump;
+
+mod sysex;
+pub use sysex::*;
+
+mod tuning;
+pub use tuning::*;
+
+mod nrpn;
+use nrpn::*;
+
 mod channel;
 use channel::*;
-use voice::{RegionPair, Voice};
+use voice::{RegionPair, ResolvedRegion, Voice, VoicePool};
+
+mod worker_pool;
+use worker_pool::*;
+
+mod allocation_guard;
+pub use allocation_guard::*;
 
 use crate::{prelude::*, utils};
 use bevy_platform::{collections::HashMap, prelude::*};
@@ -41,9 +79,12 @@ pub struct Synthesizer {
     preset_lookup: HashMap<i32, usize>,
     default_preset: usize,
 
+    // The resolved parameters of each instrument region, for each region of each preset.
+    resolved_regions: Vec<Vec<Vec<ResolvedRegion>>>,
+
     channels: Vec<SynthChannel>,
 
-    voices: Vec<Voice>,
+    voices: VoicePool,
 
     block_left: Vec<f32>,
     block_right: Vec<f32>,
@@ -54,13 +95,54 @@ pub struct Synthesizer {
 
     master_volume: f32,
 
-    effects: Option<Effects>,
+    // The master tuning in cents and the master transposition in semitones.
+    master_tune: f32,
+    master_transpose: i32,
+
+    // The master volume set by system exclusive messages.
+    system_volume: f32,
+    // The master tuning in cents and semitones, set by system exclusive messages.
+    master_fine_tune: f32,
+    master_coarse_tune: i32,
+    midi_standard: MidiStandard,
+
+    // The tuning programs, indexed by the tuning bank in the upper bits.
+    tunings: HashMap<u16, Tuning>,
+
+    // Whether channel 10 of each port is a percussion channel.
+    port_percussion: Vec<bool>,
+
+    mpe_enabled: bool,
+    mpe: MpeConfiguration,
+
+    // The reverb and chorus, one per output of `render_multi` if they are not shared.
+    effects: Vec<Effects>,
+    master: MasterChain,
+
+    // The output of `render_multi` which each channel is routed to.
+    channel_outputs: Vec<usize>,
+    output_blocks: Vec<[Vec<f32>; 2]>,
+    output_block_read: usize,
+
+    // The voices are rendered in batches, each mixed into its own block,
+    // so that the sum does not depend on the thread which rendered them.
+    batch_blocks: Vec<[Vec<f32>; 2]>,
+    voice_active: Vec<bool>,
+    workers: Option<WorkerPool>,
 }
 
 impl Synthesizer {
     /// The number of channels.
     /// The percussion channel.
     pub const PERCUSSION_CHANNEL: usize = 9;
+    /// The number of channels in a port.
+    pub const PORT_CHANNEL_COUNT: usize = 16;
+
+    const VOICE_BATCH_SIZE: usize = 8;
+    // The maximum polyphony is up to 256.
+    const MAXIMUM_BATCH_COUNT: usize = 256 / Synthesizer::VOICE_BATCH_SIZE;
+    // The number of tuning programs which SysEx can load without allocating.
+    const TUNING_PROGRAM_CAPACITY: usize = 128;
 
     /// Initializes a new synthesizer using a specified SoundFont and settings.
     ///
@@ -99,9 +181,36 @@ impl Synthesizer {
                 }
             });
 
-        const CHANNEL_COUNT: usize = 16;
-        let channels: Vec<SynthChannel> = (0..CHANNEL_COUNT)
-            .map(|i| SynthChannel::new(i == Synthesizer::PERCUSSION_CHANNEL))
+        // The generators are summed and converted once here, rather than on every note.
+        let resolved_regions: Vec<Vec<Vec<ResolvedRegion>>> = sound_font
+            .presets
+            .iter()
+            .map(|preset| {
+                preset
+                    .regions
+                    .iter()
+                    .map(|preset_region| {
+                        sound_font.instruments[preset_region.instrument]
+                            .regions
+                            .iter()
+                            .map(|instrument_region| {
+                                ResolvedRegion::new(&RegionPair::new(
+                                    preset_region,
+                                    instrument_region,
+                                ))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let channels: Vec<SynthChannel> = (0..settings.channel_count)
+            .map(|i| {
+                SynthChannel::new(
+                    i % Synthesizer::PORT_CHANNEL_COUNT == Synthesizer::PERCUSSION_CHANNEL,
+                )
+            })
             .collect();
 
         let block_left: Vec<f32> = vec![0_f32; settings.block_size];
@@ -113,11 +222,44 @@ impl Synthesizer {
 
         let master_volume = 0.5_f32;
 
-        let effects = if settings.enable_reverb_and_chorus {
-            Some(Effects::new(settings))
-        } else {
-            None
+        // In the sends mode, the effects are only used by render.
+        let effects_count = match (settings.enable_reverb_and_chorus, settings.output_effects) {
+            (false, _) => 0,
+            (true, OutputEffects::Shared) | (true, OutputEffects::Sends) => 1,
+            (true, OutputEffects::PerOutput) => settings.output_count,
         };
+        let effects: Vec<Effects> = (0..effects_count).map(|_| Effects::new(settings)).collect();
+
+        // By default, the channels are spread over the outputs in turn.
+        let channel_outputs: Vec<usize> = (0..settings.channel_count)
+            .map(|i| i % settings.output_count)
+            .collect();
+
+        let output_block_count = Synthesizer::get_render_multi_output_count(settings);
+        let output_blocks: Vec<[Vec<f32>; 2]> = (0..output_block_count)
+            .map(|_| {
+                [
+                    vec![0_f32; settings.block_size],
+                    vec![0_f32; settings.block_size],
+                ]
+            })
+            .collect();
+
+        let batch_count = settings
+            .maximum_polyphony
+            .div_ceil(Synthesizer::VOICE_BATCH_SIZE);
+        let batch_blocks: Vec<[Vec<f32>; 2]> = (0..batch_count)
+            .map(|_| {
+                [
+                    vec![0_f32; settings.block_size],
+                    vec![0_f32; settings.block_size],
+                ]
+            })
+            .collect();
+
+        // The thread which renders takes part, so one less worker is needed.
+        let workers =
+            (settings.thread_count > 1).then(|| WorkerPool::new(settings.thread_count - 1));
 
         Ok(Self {
             sound_font,
@@ -126,15 +268,33 @@ impl Synthesizer {
             maximum_polyphony: settings.maximum_polyphony,
             preset_lookup,
             default_preset,
+            resolved_regions,
             channels,
-            settings: *settings,
-            voices: Vec::with_capacity(settings.maximum_polyphony),
+            settings: settings.clone(),
+            voices: VoicePool::new(settings),
             block_left,
             block_right,
             inverse_block_size,
             block_read,
             master_volume,
+            master_tune: 0_f32,
+            master_transpose: 0,
+            system_volume: 1_f32,
+            master_fine_tune: 0_f32,
+            master_coarse_tune: 0,
+            midi_standard: MidiStandard::None,
+            tunings: HashMap::with_capacity(Synthesizer::TUNING_PROGRAM_CAPACITY),
+            port_percussion: vec![true; settings.channel_count / Synthesizer::PORT_CHANNEL_COUNT],
+            mpe_enabled: false,
+            mpe: MpeConfiguration::default(),
             effects,
+            master: MasterChain::new(settings.sample_rate),
+            channel_outputs,
+            output_blocks,
+            output_block_read: block_read,
+            batch_blocks,
+            voice_active: vec![false; settings.maximum_polyphony],
+            workers,
         })
     }
 
@@ -147,12 +307,36 @@ impl Synthesizer {
     /// * `data1` - The first data part of the message.
     /// * `data2` - The second data part of the message.
     pub fn process_midi_message(&mut self, message: ChannelVoiceMessage) {
-        let status = message.status();
-        let channel = status & 0x0F;
-        let command = status & 0xF0;
-        let data1 = message.data_1_byte();
-        let data2 = message.data_2_byte().unwrap_or_default();
+        self.process_midi_message_on_port(0, message);
+    }
+
+    /// Processes a MIDI message received on a port.
+    /// The channels of port `n` are the channels `16 * n` to `16 * n + 15` of the synthesizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port which the message was received on.
+    /// * `message` - The message.
+    pub fn process_midi_message_on_port(&mut self, port: u8, message: ChannelVoiceMessage) {
+        let _section = RealTimeSection::enter();
+
+        self.process_channel_voice_message(
+            port,
+            message.status(),
+            message.data_1_byte(),
+            message.data_2_byte().unwrap_or_default(),
+        );
+    }
 
+    fn process_channel_voice_message(&mut self, port: u8, status: u8, data1: u8, data2: u8) {
+        if let Some(channel) = self.get_channel_index(port, status & 0x0F) {
+            self.process_channel_command(channel, status & 0xF0, data1, data2);
+        }
+    }
+
+    /// Processes a channel voice message, where `channel` is the index of the channel
+    /// in the synthesizer rather than in its port.
+    fn process_channel_command(&mut self, channel: u8, command: u8, data1: u8, data2: u8) {
         if channel as usize >= self.channels.len() {
             return;
         }
@@ -167,30 +351,41 @@ impl Synthesizer {
                 0x00 => channel_info.set_bank(data2), // Bank Selection
                 0x01 => channel_info.set_modulation_coarse(data2), // Modulation Coarse
                 0x21 => channel_info.set_modulation_fine(data2), // Modulation Fine
-                0x06 => channel_info.data_entry_coarse(data2), // Data Entry Coarse
-                0x26 => channel_info.data_entry_fine(data2), // Data Entry Fine
+                0x06 => self.data_entry_coarse(channel, data2), // Data Entry Coarse
+                0x26 => self.data_entry_fine(channel, data2), // Data Entry Fine
+                0x60 => self.data_increment(channel, true), // Data Increment
+                0x61 => self.data_increment(channel, false), // Data Decrement
                 0x07 => channel_info.set_volume_coarse(data2), // Channel Volume Coarse
                 0x27 => channel_info.set_volume_fine(data2), // Channel Volume Fine
                 0x0A => channel_info.set_pan_coarse(data2), // Pan Coarse
                 0x2A => channel_info.set_pan_fine(data2), // Pan Fine
+                0x05 => channel_info.set_portamento_time_coarse(data2), // Portamento Time Coarse
+                0x25 => channel_info.set_portamento_time_fine(data2), // Portamento Time Fine
                 0x0B => channel_info.set_expression_coarse(data2), // Expression Coarse
                 0x2B => channel_info.set_expression_fine(data2), // Expression Fine
                 0x40 => channel_info.set_hold_pedal(data2), // Hold Pedal
+                0x41 => channel_info.set_portamento(data2), // Portamento On/Off
+                0x42 => self.set_sostenuto(channel, data2), // Sostenuto
+                0x43 => channel_info.set_soft_pedal(data2), // Soft Pedal
+                0x54 => channel_info.set_portamento_control(data2), // Portamento Control
+                0x4A => channel_info.set_brightness(utils::scale_up(data2 as u32, 7, 32)), // Brightness (MPE Timbre)
                 0x5B => channel_info.set_reverb_send(data2), // Reverb Send
                 0x5D => channel_info.set_chorus_send(data2), // Chorus Send
-                //Note, this used to not use data 2
-                0x63 => channel_info.set_nrpn_coarse(), // NRPN Coarse
-                //Note: this used to not use data 2
-                0x62 => channel_info.set_nrpn_fine(), // NRPN Fine
+                0x63 => channel_info.set_nrpn_coarse(data2), // NRPN Coarse
+                0x62 => channel_info.set_nrpn_fine(data2), // NRPN Fine
                 0x65 => channel_info.set_rpn_coarse(data2), // RPN Coarse
                 0x64 => channel_info.set_rpn_fine(data2), // RPN Fine
 
                 0x78 => self.note_off_all_channel(channel, true), // All Sound Off
                 0x79 => self.reset_all_controllers_channel(channel), // Reset All Controllers
                 0x7B => self.note_off_all_channel(channel, false), // All Note Off
+                0x7E => self.set_mono_mode(channel, true), // Mono Mode On
+                0x7F => self.set_mono_mode(channel, false), // Poly Mode On
                 _ => (),
             },
+            0xA0 => channel_info.set_key_pressure(data1, utils::scale_up(data2 as u32, 7, 32)), // Polyphonic Key Pressure
             0xC0 => channel_info.set_patch(data1), // Program Change
+            0xD0 => channel_info.set_channel_pressure(utils::scale_up(data1 as u32, 7, 32)), // Channel Pressure
             0xE0 => channel_info.set_pitch_bend(data1, data2), // Pitch Bend
             _ => (),
         }
@@ -203,12 +398,32 @@ impl Synthesizer {
     /// * `channel` - The channel of the note.
     /// * `key` - The key of the note.
     pub fn note_off(&mut self, channel: u8, key: u8) {
+        let _section = RealTimeSection::enter();
+
         if channel as usize >= self.channels.len() {
             return;
         }
 
+        // The voices are matched with the key of the note-on, as the drum key map may have changed.
+        let channel_info = &mut self.channels[channel as usize];
+        if channel_info.get_mono_mode() {
+            // Going back to a key which is still held down is legato as well.
+            if let Some(previous) = channel_info.stop_note(key) {
+                let previous_key = channel_info.get_mapped_key(previous);
+                let glide_time = channel_info
+                    .get_portamento()
+                    .then(|| channel_info.get_portamento_time());
+                for voice in self.voices.iter_mut() {
+                    if voice.channel == channel && voice.note_key == key && voice.is_playing() {
+                        voice.legato(previous, previous_key, glide_time);
+                    }
+                }
+                return;
+            }
+        }
+
         for voice in self.voices.iter_mut() {
-            if voice.channel == channel && voice.key == key {
+            if voice.channel == channel && voice.note_key == key {
                 voice.end();
             }
         }
@@ -222,16 +437,49 @@ impl Synthesizer {
     /// * `key` - The key of the note.
     /// * `velocity` - The velocity of the note.
     pub fn note_on(&mut self, channel: u8, key: u8, velocity: u8) {
+        let _section = RealTimeSection::enter();
+
         if velocity == 0 {
             self.note_off(channel, key);
             return;
         }
 
+        self.play_note(channel, key, utils::scale_up(velocity as u32, 7, 16) as u16);
+    }
+
+    /// Starts a note with a 16-bit velocity, which must not be zero.
+    fn play_note(&mut self, channel: u8, key: u8, velocity: u16) {
         if channel as usize >= self.channels.len() {
             return;
         }
 
+        let channel_info = &mut self.channels[channel as usize];
+        let note_key = key;
+        let key = channel_info.get_mapped_key(note_key);
+        let glide_from = channel_info.start_note(note_key);
+        let glide_time = channel_info.get_portamento_time();
+
+        // In mono mode, a note played while another one is held down
+        // takes over the sounding voices instead of retriggering them.
+        if channel_info.get_mono_mode() {
+            let mut legato = false;
+            for voice in self.voices.iter_mut() {
+                if voice.channel == channel && voice.is_playing() {
+                    voice.legato(note_key, key, glide_from.map(|_| glide_time));
+                    legato = true;
+                }
+            }
+            if legato {
+                return;
+            }
+        }
+
         let channel_info = &self.channels[channel as usize];
+        let velocity = channel_info.get_note_velocity(velocity);
+        let soft_pedal = channel_info.get_soft_pedal();
+        // Regions are still selected with a MIDI 1.0 velocity.
+        let region_velocity = ((velocity >> 9) as u8).max(1);
+        let offsets = channel_info.get_generator_offsets(key);
 
         let preset_id = ((channel_info.get_bank_number() as i32) << 16)
             | channel_info.get_patch_number() as i32;
@@ -259,69 +507,60 @@ impl Synthesizer {
             }
         };
 
+        let resolved_regions = &self.resolved_regions[preset];
         let preset = &self.sound_font.presets[preset];
-        for preset_region in preset.regions.iter() {
-            if preset_region.contains(key, velocity) {
+        for (i, preset_region) in preset.regions.iter().enumerate() {
+            if preset_region.contains(key, region_velocity) {
                 let instrument = &self.sound_font.instruments[preset_region.instrument];
-                for instrument_region in instrument.regions.iter() {
-                    if instrument_region.contains(key, velocity) {
-                        let region_pair = RegionPair::new(preset_region, instrument_region);
+                for (j, instrument_region) in instrument.regions.iter().enumerate() {
+                    if instrument_region.contains(key, region_velocity) {
+                        // The NRPN offsets change the generators, so the region is resolved again.
+                        let resolved_with_offsets;
+                        let region = if offsets.is_zero() {
+                            &resolved_regions[i][j]
+                        } else {
+                            resolved_with_offsets = ResolvedRegion::new(
+                                &RegionPair::new(preset_region, instrument_region)
+                                    .with_offsets(&offsets),
+                            );
+                            &resolved_with_offsets
+                        };
 
                         // If an exclusive class is assigned to the region, find a voice with the same class.
                         // If found, reuse it to avoid playing multiple voices with the same class at a time.
                         let exclusive_class = instrument_region.get_exclusive_class();
 
-                        if exclusive_class != 0 {
-                            for voice in self.voices.iter_mut() {
-                                if voice.exclusive_class == exclusive_class
-                                    && voice.channel == channel
-                                {
-                                    //this is identical to what existed before. Instant drop.
-                                    *voice = Voice::new(
-                                        &self.settings,
-                                        &region_pair,
-                                        channel,
-                                        key,
-                                        velocity,
-                                    );
-                                    return;
-                                }
+                        // The voice takes over the block of the voice it replaces,
+                        // or a free one from the pool.
+                        let settings = &self.settings;
+                        let create = |block| {
+                            let mut voice = Voice::new(
+                                settings, region, channel, note_key, key, velocity, block,
+                            );
+                            if let Some(from_key) = glide_from {
+                                voice.glide_from(from_key, glide_time);
                             }
-                        }
-
-                        // Check if we've reached the maximum polyphony limit
-                        if self.voices.len() >= self.maximum_polyphony {
-                            // Find the voice with the lowest priority to replace
-                            let mut candidate = 0;
-                            let mut lowest_priority = f32::MAX;
-
-                            for (i, voice) in self.voices.iter().enumerate() {
-                                let priority = voice.get_priority();
-                                if priority < lowest_priority {
-                                    lowest_priority = priority;
-                                    candidate = i;
-                                } else if priority == lowest_priority {
-                                    // Same priority - the older one should be more suitable for reuse
-                                    if voice.get_voice_length()
-                                        > self.voices[candidate].get_voice_length()
-                                    {
-                                        candidate = i;
-                                    }
-                                }
+                            if soft_pedal {
+                                voice.soften();
                             }
+                            voice
+                        };
+
+                        if let Some(existing) = self.voices.iter().position(|existing| {
+                            exclusive_class != 0
+                                && existing.exclusive_class == exclusive_class
+                                && existing.channel == channel
+                        }) {
+                            //this is identical to what existed before. Instant drop.
+                            self.voices.replace(existing, create);
+                            return;
+                        }
 
-                            // Replace the voice with lowest priority
-                            self.voices[candidate] =
-                                Voice::new(&self.settings, &region_pair, channel, key, velocity);
-                        } else {
-                            // We have room for a new voice
-                            self.voices.push(Voice::new(
-                                &self.settings,
-                                &region_pair,
-                                channel,
-                                key,
-                                velocity,
-                            ));
+                        match self.allocate_voice(channel) {
+                            Some(VoiceSlot::New) => self.voices.push(create),
+                            Some(VoiceSlot::Replace(i)) => self.voices.replace(i, create),
+                            // Every voice we could take is reserved for another channel.
+                            None => (),
                         }
                     }
                 }
@@ -329,6 +568,74 @@ impl Synthesizer {
         }
     }
 
+    /// Decides where a new voice on the channel should go.
+    ///
+    /// A channel which has reached its own maximum polyphony steals from itself.
+    /// Voices reserved for other channels are never handed out, and they are only
+    /// stolen from channels that use more voices than they have reserved.
+    fn allocate_voice(&self, channel: u8) -> Option<VoiceSlot> {
+        let mut voice_counts = [0_usize; 256];
+        for voice in self.voices.iter() {
+            voice_counts[voice.channel as usize] += 1;
+        }
+
+        let channel_info = &self.channels[channel as usize];
+        if channel_info
+            .get_maximum_polyphony()
+            .is_some_and(|maximum_polyphony| voice_counts[channel as usize] >= maximum_polyphony)
+        {
+            return self
+                .find_voice_to_steal(|voice| voice.channel == channel)
+                .map(VoiceSlot::Replace);
+        }
+
+        let unused_reserved_voices: usize = self
+            .channels
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != channel as usize)
+            .map(|(i, other)| other.get_reserved_voices().saturating_sub(voice_counts[i]))
+            .sum();
+
+        if self.voices.len() + unused_reserved_voices < self.maximum_polyphony {
+            return Some(VoiceSlot::New);
+        }
+
+        self.find_voice_to_steal(|voice| {
+            voice.channel == channel
+                || voice_counts[voice.channel as usize]
+                    > self.channels[voice.channel as usize].get_reserved_voices()
+        })
+        .map(VoiceSlot::Replace)
+    }
+
+    /// Finds the voice with the lowest priority among the candidates.
+    fn find_voice_to_steal(&self, is_candidate: impl Fn(&Voice) -> bool) -> Option<usize> {
+        let mut candidate: Option<usize> = None;
+        let mut lowest_priority = f32::MAX;
+
+        for (i, voice) in self.voices.iter().enumerate() {
+            if !is_candidate(voice) {
+                continue;
+            }
+
+            let priority = voice.get_priority();
+            if priority < lowest_priority {
+                lowest_priority = priority;
+                candidate = Some(i);
+            } else if priority == lowest_priority
+                && candidate.is_some_and(|c| {
+                    // Same priority - the older one should be more suitable for reuse
+                    voice.get_voice_length() > self.voices[c].get_voice_length()
+                })
+            {
+                candidate = Some(i);
+            }
+        }
+
+        candidate
+    }
+
     /// Stops all the notes in the specified channel.
     ///
     /// # Arguments
@@ -362,6 +669,91 @@ impl Synthesizer {
         }
     }
 
+    fn data_entry_coarse(&mut self, channel: u8, value: u8) {
+        let channel_info = &mut self.channels[channel as usize];
+
+        if self.mpe_enabled && channel_info.get_rpn() == Some(SynthChannel::RPN_MPE_CONFIGURATION) {
+            self.configure_mpe_zone(channel, value);
+            return;
+        }
+
+        channel_info.data_entry_coarse(value);
+        self.sync_mpe_pitch_bend_range(channel);
+        self.sync_tuning_program(channel);
+    }
+
+    fn data_entry_fine(&mut self, channel: u8, value: u8) {
+        self.channels[channel as usize].data_entry_fine(value);
+        self.sync_mpe_pitch_bend_range(channel);
+    }
+
+    fn data_increment(&mut self, channel: u8, up: bool) {
+        self.channels[channel as usize].data_increment(up);
+        self.sync_mpe_pitch_bend_range(channel);
+        self.sync_tuning_program(channel);
+    }
+
+    /// Selecting a tuning program or bank switches the channel to that tuning.
+    fn sync_tuning_program(&mut self, channel: u8) {
+        if matches!(self.channels[channel as usize].get_rpn(), Some(3 | 4)) {
+            self.apply_tuning_program(channel);
+        }
+    }
+
+    /// Copies the selected tuning program into the channel.
+    /// Programs which were never loaded are equal temperament.
+    fn apply_tuning_program(&mut self, channel: u8) {
+        let channel_info = &mut self.channels[channel as usize];
+        let id = Synthesizer::get_tuning_id(
+            channel_info.get_tuning_bank(),
+            channel_info.get_tuning_program(),
+        );
+        let tuning = self.tunings.get(&id).unwrap_or(&Tuning::EQUAL_TEMPERAMENT);
+        channel_info.set_tuning(tuning);
+    }
+
+    fn get_tuning_id(bank: u8, program: u8) -> u16 {
+        ((bank as u16 & 0x7F) << 7) | (program as u16 & 0x7F)
+    }
+
+    /// The pitch bend range set on a member channel applies to the whole zone.
+    fn sync_mpe_pitch_bend_range(&mut self, channel: u8) {
+        if !self.mpe_enabled || self.channels[channel as usize].get_rpn() != Some(0) {
+            return;
+        }
+
+        if let Some(zone) = self.mpe.get_zone_of_member(channel) {
+            let range = self.channels[channel as usize].get_pitch_bend_range_data();
+            for member in zone.get_member_channels() {
+                self.channels[member as usize].set_pitch_bend_range_data(range);
+            }
+        }
+    }
+
+    /// Updates the sostenuto pedal of the channel.
+    /// Pressing it latches the notes which are held down at that moment.
+    fn set_sostenuto(&mut self, channel: u8, value: u8) {
+        let pressed = value >= 64;
+        let channel_info = &mut self.channels[channel as usize];
+
+        if pressed && !channel_info.get_sostenuto() {
+            for voice in self.voices.iter_mut() {
+                if voice.channel == channel {
+                    voice.catch_sostenuto();
+                }
+            }
+        }
+
+        channel_info.set_sostenuto(pressed);
+    }
+
+    /// Switches the channel between mono and poly mode.
+    /// As with any channel mode message, all the notes in the channel are stopped.
+    fn set_mono_mode(&mut self, channel: u8, value: bool) {
+        self.channels[channel as usize].set_mono_mode(value);
+        self.note_off_all_channel(channel, false);
+    }
+
     /// Resets all the controllers.
     pub fn reset_all_controllers(&mut self) {
         for channel in &mut self.channels {
@@ -390,12 +782,19 @@ impl Synthesizer {
             channel.reset();
         }
 
-        if let Some(effects) = self.effects.as_mut() {
+        for channel in 0..self.channels.len() {
+            self.apply_tuning_program(channel as u8);
+        }
+
+        for effects in self.effects.iter_mut() {
             effects.reverb.mute();
             effects.chorus.mute();
         }
 
+        self.master.mute();
+
         self.block_read = self.block_size;
+        self.output_block_read = self.block_size;
     }
 
     /// Renders the waveform.
@@ -413,6 +812,8 @@ impl Synthesizer {
             panic!("The output buffers for the left and right must be the same length.");
         }
 
+        let _section = RealTimeSection::enter();
+
         let left_length = left.len();
 
         let mut wrote = 0;
@@ -436,117 +837,288 @@ impl Synthesizer {
         }
     }
 
-    fn render_block(&mut self) {
+    /// Renders the waveform, writing the channels to separate stereo outputs.
+    /// Each channel is written to the output set by [`Synthesizer::set_channel_output`],
+    /// and the master chain is not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - The left and right buffers of each output to store the rendered waveform.
+    ///
+    /// # Remarks
+    ///
+    /// There must be as many outputs as [`Synthesizer::get_output_count`], and all the buffers
+    /// must be the same length.
+    /// This renders the same voices as [`Synthesizer::render`], so only one of them should be used.
+    pub fn render_multi(&mut self, outputs: &mut [[&mut [f32]; 2]]) {
+        if outputs.len() != self.output_blocks.len() {
+            panic!(
+                "The number of outputs must be {}, but was {}.",
+                self.output_blocks.len(),
+                outputs.len()
+            );
+        }
+
+        let output_length = outputs[0][0].len();
+        if outputs
+            .iter()
+            .flatten()
+            .any(|buffer| buffer.len() != output_length)
+        {
+            panic!("The output buffers must be the same length.");
+        }
+
+        let _section = RealTimeSection::enter();
+
+        let mut wrote = 0;
+        while wrote < output_length {
+            if self.output_block_read == self.block_size {
+                self.render_output_block();
+                self.output_block_read = 0;
+            }
+
+            let src_rem = self.block_size - self.output_block_read;
+            let dst_rem = output_length - wrote;
+            let rem = cmp::min(src_rem, dst_rem);
+
+            for (output, block) in outputs.iter_mut().zip(self.output_blocks.iter()) {
+                for (destination, source) in output.iter_mut().zip(block.iter()) {
+                    destination[wrote..wrote + rem].copy_from_slice(
+                        &source[self.output_block_read..self.output_block_read + rem],
+                    );
+                }
+            }
+
+            self.output_block_read += rem;
+            wrote += rem;
+        }
+    }
+
+    /// Processes the voices in batches, and drops the ones which have finished.
+    /// If `mix` is set, each batch is also mixed into its own block.
+    /// Returns the number of batches.
+    fn process_voices(&mut self, mix: bool) -> usize {
         // the idea here is that if the voice cannot process, drop it.
         // A voice will not be able to process if it's been killed and is ready for release.
+        let master_tune = 0.01_f32 * (self.master_tune + self.master_fine_tune);
+        let master_transpose = (self.master_transpose + self.master_coarse_tune) as f32;
+        let master_volume = self.master_volume * self.system_volume;
+        let inverse_block_size = self.inverse_block_size;
+        let data = &self.sound_font.wave_data[..];
+        let channels = &self.channels[..];
+
+        let process_batch = |voices: &mut [Voice],
+                             active: &mut [bool],
+                             block: &mut [Vec<f32>; 2]| {
+            let [left, right] = block;
+            if mix {
+                left.fill(0_f32);
+                right.fill(0_f32);
+            }
+
+            for (voice, active) in voices.iter_mut().zip(active.iter_mut()) {
+                *active = voice.process(data, channels, master_tune, master_transpose);
+                if *active && mix {
+                    Synthesizer::write_voice(voice, master_volume, left, right, inverse_block_size);
+                }
+            }
+        };
+
+        let batch_count = self.voices.len().div_ceil(Synthesizer::VOICE_BATCH_SIZE);
+        let mut batches = self
+            .voices
+            .chunks_mut(Synthesizer::VOICE_BATCH_SIZE)
+            .zip(self.voice_active.chunks_mut(Synthesizer::VOICE_BATCH_SIZE))
+            .zip(self.batch_blocks.iter_mut());
+
+        match &self.workers {
+            Some(workers) => {
+                // Each batch is taken by one thread only, so the locks are never contended.
+                let batches: [Mutex<Option<_>>; Synthesizer::MAXIMUM_BATCH_COUNT] =
+                    core::array::from_fn(|_| Mutex::new(batches.next()));
+                workers.run(batch_count, &|batch| {
+                    let _section = RealTimeSection::enter();
+                    let batch = batches[batch].lock().map(|mut batch| batch.take());
+                    if let Ok(Some(((voices, active), block))) = batch {
+                        process_batch(voices, active, block);
+                    }
+                });
+            }
+            None => {
+                for ((voices, active), block) in batches {
+                    process_batch(voices, active, block);
+                }
+            }
+        }
+
+        let mut active = self.voice_active.iter();
         self.voices
-            .retain_mut(|voice| voice.process(&self.sound_font.wave_data, &self.channels));
+            .retain(|_| active.next().copied().unwrap_or_default());
+
+        batch_count
+    }
+
+    fn render_block(&mut self) {
+        let batch_count = self.process_voices(true);
+
+        let master_volume = self.master_volume * self.system_volume;
 
+        // The batches are summed in order, whichever thread rendered them.
         self.block_left.fill(0_f32);
         self.block_right.fill(0_f32);
-
-        for voice in self.voices.iter_mut() {
-            let previous_gain_left = self.master_volume * voice.previous_mix_gain_left;
-            let current_gain_left = self.master_volume * voice.current_mix_gain_left;
-            Synthesizer::write_block(
-                previous_gain_left,
-                current_gain_left,
-                &voice.block,
-                &mut self.block_left,
-                self.inverse_block_size,
-            );
-            let previous_gain_right = self.master_volume * voice.previous_mix_gain_right;
-            let current_gain_right = self.master_volume * voice.current_mix_gain_right;
-            Synthesizer::write_block(
-                previous_gain_right,
-                current_gain_right,
-                &voice.block,
-                &mut self.block_right,
-                self.inverse_block_size,
-            );
+        for [left, right] in self.batch_blocks[..batch_count].iter() {
+            ArrayMath::multiply_add(1_f32, left, &mut self.block_left);
+            ArrayMath::multiply_add(1_f32, right, &mut self.block_right);
         }
 
-        if let Some(effects) = self.effects.as_mut() {
-            let chorus = &mut effects.chorus;
-            let chorus_input_left = &mut effects.chorus_input_left[..];
-            let chorus_input_right = &mut effects.chorus_input_right[..];
-            let chorus_output_left = &mut effects.chorus_output_left[..];
-            let chorus_output_right = &mut effects.chorus_output_right[..];
-            chorus_input_left.fill(0_f32);
-            chorus_input_right.fill(0_f32);
-            for voice in self.voices.iter_mut() {
-                let previous_gain_left = voice.previous_chorus_send * voice.previous_mix_gain_left;
-                let current_gain_left = voice.current_chorus_send * voice.current_mix_gain_left;
-                Synthesizer::write_block(
-                    previous_gain_left,
-                    current_gain_left,
-                    &voice.block[..],
-                    chorus_input_left,
-                    self.inverse_block_size,
-                );
-                let previous_gain_right =
-                    voice.previous_chorus_send * voice.previous_mix_gain_right;
-                let current_gain_right = voice.current_chorus_send * voice.current_mix_gain_right;
-                Synthesizer::write_block(
-                    previous_gain_right,
-                    current_gain_right,
-                    &voice.block[..],
-                    chorus_input_right,
-                    self.inverse_block_size,
-                );
+        if let Some(effects) = self.effects.first_mut() {
+            effects.clear_sends();
+            for voice in self.voices.iter() {
+                effects.write_sends(voice, self.inverse_block_size);
             }
-            chorus.process(
-                chorus_input_left,
-                chorus_input_right,
-                chorus_output_left,
-                chorus_output_right,
-            );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                chorus_output_left,
+            effects.process();
+            effects.write_returns(
+                master_volume,
                 &mut self.block_left[..],
-            );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                chorus_output_right,
                 &mut self.block_right[..],
             );
+        }
 
-            let reverb = &mut effects.reverb;
-            let reverb_input = &mut effects.reverb_input[..];
-            let reverb_output_left = &mut effects.reverb_output_left[..];
-            let reverb_output_right = &mut effects.reverb_output_right[..];
-            reverb_input.fill(0_f32);
-            for voice in self.voices.iter_mut() {
-                let previous_gain = reverb.get_input_gain()
-                    * voice.previous_reverb_send
-                    * (voice.previous_mix_gain_left + voice.previous_mix_gain_right);
-                let current_gain = reverb.get_input_gain()
-                    * voice.current_reverb_send
-                    * (voice.current_mix_gain_left + voice.current_mix_gain_right);
-                Synthesizer::write_block(
-                    previous_gain,
-                    current_gain,
-                    &voice.block[..],
-                    &mut reverb_input[..],
-                    self.inverse_block_size,
-                );
-            }
+        self.master
+            .process(&mut self.block_left[..], &mut self.block_right[..]);
+    }
 
-            reverb.process(reverb_input, reverb_output_left, reverb_output_right);
-            ArrayMath::multiply_add(
-                self.master_volume,
-                reverb_output_left,
-                &mut self.block_left[..],
-            );
-            ArrayMath::multiply_add(
-                self.master_volume,
-                reverb_output_right,
-                &mut self.block_right[..],
-            );
+    fn render_output_block(&mut self) {
+        self.process_voices(false);
+
+        let master_volume = self.master_volume * self.system_volume;
+
+        for block in self.output_blocks.iter_mut().flatten() {
+            block.fill(0_f32);
+        }
+
+        for voice in self.voices.iter() {
+            let [left, right] =
+                &mut self.output_blocks[self.channel_outputs[voice.channel as usize]];
+            Synthesizer::write_voice(voice, master_volume, left, right, self.inverse_block_size);
+        }
+
+        match self.settings.output_effects {
+            // The returns go to the two outputs after the channel outputs.
+            OutputEffects::Shared => {
+                if let Some(effects) = self.effects.first_mut() {
+                    effects.clear_sends();
+                    for voice in self.voices.iter() {
+                        effects.write_sends(voice, self.inverse_block_size);
+                    }
+                    effects.process();
+
+                    let returns = &mut self.output_blocks[self.settings.output_count..];
+                    let [reverb_left, reverb_right] = &mut returns[0];
+                    ArrayMath::multiply_add(
+                        master_volume,
+                        &effects.reverb_output_left,
+                        reverb_left,
+                    );
+                    ArrayMath::multiply_add(
+                        master_volume,
+                        &effects.reverb_output_right,
+                        reverb_right,
+                    );
+                    let [chorus_left, chorus_right] = &mut returns[1];
+                    ArrayMath::multiply_add(
+                        master_volume,
+                        &effects.chorus_output_left,
+                        chorus_left,
+                    );
+                    ArrayMath::multiply_add(
+                        master_volume,
+                        &effects.chorus_output_right,
+                        chorus_right,
+                    );
+                }
+            }
+            // The sends go to the two outputs after the channel outputs, without any effect.
+            OutputEffects::Sends => {
+                let sends = &mut self.output_blocks[self.settings.output_count..];
+                for voice in self.voices.iter() {
+                    let [reverb_left, reverb_right] = &mut sends[0];
+                    Synthesizer::write_block(
+                        master_volume * voice.previous_reverb_send * voice.previous_mix_gain_left,
+                        master_volume * voice.current_reverb_send * voice.current_mix_gain_left,
+                        &voice.block,
+                        reverb_left,
+                        self.inverse_block_size,
+                    );
+                    Synthesizer::write_block(
+                        master_volume * voice.previous_reverb_send * voice.previous_mix_gain_right,
+                        master_volume * voice.current_reverb_send * voice.current_mix_gain_right,
+                        &voice.block,
+                        reverb_right,
+                        self.inverse_block_size,
+                    );
+                    let [chorus_left, chorus_right] = &mut sends[1];
+                    Synthesizer::write_block(
+                        master_volume * voice.previous_chorus_send * voice.previous_mix_gain_left,
+                        master_volume * voice.current_chorus_send * voice.current_mix_gain_left,
+                        &voice.block,
+                        chorus_left,
+                        self.inverse_block_size,
+                    );
+                    Synthesizer::write_block(
+                        master_volume * voice.previous_chorus_send * voice.previous_mix_gain_right,
+                        master_volume * voice.current_chorus_send * voice.current_mix_gain_right,
+                        &voice.block,
+                        chorus_right,
+                        self.inverse_block_size,
+                    );
+                }
+            }
+            // Each output has its own effects.
+            OutputEffects::PerOutput => {
+                for effects in self.effects.iter_mut() {
+                    effects.clear_sends();
+                }
+                for voice in self.voices.iter() {
+                    let output = self.channel_outputs[voice.channel as usize];
+                    // There are no effects if reverb and chorus are disabled.
+                    if let Some(effects) = self.effects.get_mut(output) {
+                        effects.write_sends(voice, self.inverse_block_size);
+                    }
+                }
+                for (effects, [left, right]) in
+                    self.effects.iter_mut().zip(self.output_blocks.iter_mut())
+                {
+                    effects.process();
+                    effects.write_returns(master_volume, left, right);
+                }
+            }
         }
     }
 
+    fn write_voice(
+        voice: &Voice,
+        master_volume: f32,
+        left: &mut [f32],
+        right: &mut [f32],
+        inverse_block_size: f32,
+    ) {
+        Synthesizer::write_block(
+            master_volume * voice.previous_mix_gain_left,
+            master_volume * voice.current_mix_gain_left,
+            &voice.block,
+            left,
+            inverse_block_size,
+        );
+        Synthesizer::write_block(
+            master_volume * voice.previous_mix_gain_right,
+            master_volume * voice.current_mix_gain_right,
+            &voice.block,
+            right,
+            inverse_block_size,
+        );
+    }
+
     fn write_block(
         previous_gain: f32,
         current_gain: f32,
@@ -586,9 +1158,543 @@ impl Synthesizer {
         self.maximum_polyphony
     }
 
+    /// Gets the maximum number of voices the channel can use at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_maximum_polyphony(&self, channel: u8) -> Option<usize> {
+        self.channels
+            .get(channel as usize)
+            .and_then(|channel_info| channel_info.get_maximum_polyphony())
+    }
+
+    /// Sets the maximum number of voices the channel can use at a time.
+    /// Once the limit is reached, new notes steal the channel's own voices.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to limit.
+    /// * `value` - The new limit, or `None` to only apply the synthesizer-wide maximum polyphony.
+    pub fn set_channel_maximum_polyphony(&mut self, channel: u8, value: Option<usize>) {
+        let maximum_polyphony = self.maximum_polyphony;
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_maximum_polyphony(value.map(|value| value.min(maximum_polyphony)));
+        }
+    }
+
+    /// Gets the number of voices reserved for the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_reserved_voices(&self, channel: u8) -> usize {
+        self.channels
+            .get(channel as usize)
+            .map(|channel_info| channel_info.get_reserved_voices())
+            .unwrap_or_default()
+    }
+
+    /// Sets the number of voices reserved for the channel.
+    /// Other channels can neither allocate nor steal these voices.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to reserve voices for.
+    /// * `value` - The number of voices to reserve.
+    pub fn set_channel_reserved_voices(&mut self, channel: u8, value: usize) {
+        let maximum_polyphony = self.maximum_polyphony;
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_reserved_voices(value.min(maximum_polyphony));
+        }
+    }
+
+    /// Gets the transposition of the channel in semitones.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_transpose(&self, channel: u8) -> i32 {
+        self.channels
+            .get(channel as usize)
+            .map(|channel_info| channel_info.get_transpose())
+            .unwrap_or_default()
+    }
+
+    /// Sets the transposition of the channel in semitones.
+    /// Unlike the master transposition, it also applies to percussion channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to transpose.
+    /// * `value` - The transposition in semitones.
+    pub fn set_channel_transpose(&mut self, channel: u8, value: i32) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_transpose(value);
+        }
+    }
+
+    /// Gets the index of a channel of a port, which the methods taking a channel expect.
+    /// Returns `None` if the synthesizer does not have that many ports.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port of the channel.
+    /// * `channel` - The channel in the port, from 0 to 15.
+    pub fn get_channel_index(&self, port: u8, channel: u8) -> Option<u8> {
+        let index = Synthesizer::PORT_CHANNEL_COUNT * port as usize + channel as usize;
+        if channel as usize >= Synthesizer::PORT_CHANNEL_COUNT || index >= self.channels.len() {
+            return None;
+        }
+
+        Some(index as u8)
+    }
+
+    /// Gets the number of channels.
+    pub fn get_channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Gets the number of ports.
+    pub fn get_port_count(&self) -> usize {
+        self.port_percussion.len()
+    }
+
+    /// Gets the value indicating whether channel 10 of the port is a percussion channel
+    /// after a reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to query.
+    pub fn get_port_percussion(&self, port: u8) -> bool {
+        self.port_percussion
+            .get(port as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets whether channel 10 of the port is a percussion channel.
+    /// This applies immediately, and is kept across resets.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to configure.
+    /// * `value` - True for a percussion channel, false for a melodic one.
+    pub fn set_port_percussion(&mut self, port: u8, value: bool) {
+        let Some(percussion) = self.port_percussion.get_mut(port as usize) else {
+            return;
+        };
+        *percussion = value;
+
+        if let Some(channel) = self.get_channel_index(port, Synthesizer::PERCUSSION_CHANNEL as u8) {
+            self.channels[channel as usize].set_percussion_channel(value);
+        }
+    }
+
+    /// Gets the value indicating whether the channel is a percussion channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_percussion(&self, channel: u8) -> bool {
+        self.channels
+            .get(channel as usize)
+            .map(|channel_info| channel_info.get_percussion_channel())
+            .unwrap_or_default()
+    }
+
+    /// Makes the channel a percussion channel or a melodic one.
+    /// The selected bank number is kept, and switches between the melodic and drum banks.
+    /// Notes which are already playing are not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to configure.
+    /// * `value` - True for a percussion channel, false for a melodic one.
+    pub fn set_channel_percussion(&mut self, channel: u8, value: bool) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_percussion_channel(value);
+        }
+    }
+
+    /// Gets the drum kit key which a key plays on a percussion channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    /// * `key` - The incoming key.
+    pub fn get_drum_key(&self, channel: u8, key: u8) -> u8 {
+        self.channels
+            .get(channel as usize)
+            .map_or(key, |channel_info| channel_info.get_drum_key(key))
+    }
+
+    /// Maps an incoming key to another key of the drum kit on a percussion channel,
+    /// so that the sounds of a kit can be played from arbitrary keys.
+    /// The mapping is kept across resets, and has no effect on melodic channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to configure.
+    /// * `key` - The incoming key.
+    /// * `drum_key` - The key of the drum kit to play instead.
+    pub fn set_drum_key(&mut self, channel: u8, key: u8, drum_key: u8) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_drum_key(key, drum_key);
+        }
+    }
+
+    /// Restores the identity drum key mapping of the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to configure.
+    pub fn clear_drum_key_map(&mut self, channel: u8) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.clear_drum_key_map();
+        }
+    }
+
+    /// Gets the value indicating whether the channel is a percussion channel by default.
+    fn is_default_percussion_channel(&self, channel: usize) -> bool {
+        channel % Synthesizer::PORT_CHANNEL_COUNT == Synthesizer::PERCUSSION_CHANNEL
+            && self.port_percussion[channel / Synthesizer::PORT_CHANNEL_COUNT]
+    }
+
+    /// Gets the tuning bank and tuning program selected on the channel (RPN 4 and 3).
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_tuning_program(&self, channel: u8) -> (u8, u8) {
+        self.channels
+            .get(channel as usize)
+            .map(|channel_info| {
+                (
+                    channel_info.get_tuning_bank(),
+                    channel_info.get_tuning_program(),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets a tuning program, if it has been loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `bank` - The tuning bank of the program.
+    /// * `program` - The tuning program.
+    pub fn get_tuning_program(&self, bank: u8, program: u8) -> Option<&Tuning> {
+        self.tunings.get(&Synthesizer::get_tuning_id(bank, program))
+    }
+
+    /// Loads a tuning program, which channels select with RPN 4 and 3.
+    /// The channels which have the program selected are retuned immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `bank` - The tuning bank of the program.
+    /// * `program` - The tuning program.
+    /// * `tuning` - The tuning table.
+    pub fn set_tuning_program(&mut self, bank: u8, program: u8, tuning: Tuning) {
+        self.tunings
+            .insert(Synthesizer::get_tuning_id(bank, program), tuning);
+        self.retune_program(bank, program);
+    }
+
+    /// Re-applies a tuning program to the channels which have it selected.
+    fn retune_program(&mut self, bank: u8, program: u8) {
+        for channel in 0..self.channels.len() {
+            let (channel_bank, channel_program) = self.get_channel_tuning_program(channel as u8);
+            if (channel_bank, channel_program) == (bank & 0x7F, program & 0x7F) {
+                self.apply_tuning_program(channel as u8);
+            }
+        }
+    }
+
+    /// Gets the tuning of the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to query.
+    pub fn get_channel_tuning(&self, channel: u8) -> Option<&Tuning> {
+        self.channels
+            .get(channel as usize)
+            .map(|channel_info| channel_info.get_tuning())
+    }
+
+    /// Sets the tuning of the channel, regardless of its tuning program.
+    /// It is replaced when the channel selects another tuning program.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to retune.
+    /// * `tuning` - The tuning table.
+    pub fn set_channel_tuning(&mut self, channel: u8, tuning: &Tuning) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_tuning(tuning);
+        }
+    }
+
+    /// Sets how the channel pressure modulates the sound of the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to configure.
+    /// * `value` - The new destination of the channel pressure.
+    pub fn set_channel_pressure_destination(&mut self, channel: u8, value: ControllerDestination) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_channel_pressure_destination(value);
+        }
+    }
+
+    /// Sets how the polyphonic key pressure modulates the notes of the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to configure.
+    /// * `value` - The new destination of the polyphonic key pressure.
+    pub fn set_key_pressure_destination(&mut self, channel: u8, value: ControllerDestination) {
+        if let Some(channel_info) = self.channels.get_mut(channel as usize) {
+            channel_info.set_key_pressure_destination(value);
+        }
+    }
+
+    /// Gets the value indicating whether MPE (MIDI Polyphonic Expression) mode is enabled.
+    pub fn get_mpe_enabled(&self) -> bool {
+        self.mpe_enabled
+    }
+
+    /// Enables or disables MPE (MIDI Polyphonic Expression) mode.
+    ///
+    /// In MPE mode, the MPE configuration message (RPN 6) sets up the zones.
+    /// Disabling it turns every channel back into a regular channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value indicating whether MPE mode is enabled.
+    pub fn set_mpe_enabled(&mut self, value: bool) {
+        self.mpe_enabled = value;
+
+        if !value {
+            self.mpe.clear();
+            self.update_mpe_channels();
+        }
+    }
+
+    /// Sets up an MPE zone, as the MPE configuration message does.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_channel` - The master channel of the zone, which must be 0 (lower zone) or 15 (upper zone).
+    /// * `member_channel_count` - The number of member channels, or 0 to turn the zone off.
+    pub fn configure_mpe_zone(&mut self, master_channel: u8, member_channel_count: u8) {
+        if !self.mpe_enabled || !self.mpe.set_zone(master_channel, member_channel_count) {
+            return;
+        }
+
+        self.update_mpe_channels();
+
+        if let Some(channel_info) = self.channels.get_mut(master_channel as usize) {
+            channel_info
+                .set_pitch_bend_range_data((MpeZone::DEFAULT_MASTER_PITCH_BEND_RANGE as u16) << 7);
+        }
+    }
+
+    /// Gets the lower MPE zone, if any.
+    pub fn get_mpe_lower_zone(&self) -> Option<MpeZone> {
+        self.mpe.get_lower_zone()
+    }
+
+    /// Gets the upper MPE zone, if any.
+    pub fn get_mpe_upper_zone(&self) -> Option<MpeZone> {
+        self.mpe.get_upper_zone()
+    }
+
+    fn update_mpe_channels(&mut self) {
+        for (i, channel_info) in self.channels.iter_mut().enumerate() {
+            let master = self
+                .mpe
+                .get_zone_of_member(i as u8)
+                .map(|zone| zone.get_master_channel());
+            if channel_info.get_mpe_master() != master {
+                channel_info.set_mpe_master(master);
+            }
+        }
+    }
+
     /// Gets the value indicating whether reverb and chorus are enabled.
+    ///
+    /// # Remarks
+    ///
+    /// With [`OutputEffects::Sends`], this only applies to [`Synthesizer::render`],
+    /// as [`Synthesizer::render_multi`] always writes the sends without any effect.
     pub fn get_enable_reverb_and_chorus(&self) -> bool {
-        self.effects.is_some()
+        !self.effects.is_empty()
+    }
+
+    /// Gets the number of outputs expected by [`Synthesizer::render_multi`].
+    /// This is the number of channel outputs, plus the reverb and chorus outputs if they are not
+    /// mixed into the channel outputs.
+    pub fn get_output_count(&self) -> usize {
+        self.output_blocks.len()
+    }
+
+    fn get_render_multi_output_count(settings: &SynthesizerSettings) -> usize {
+        match (settings.enable_reverb_and_chorus, settings.output_effects) {
+            (true, OutputEffects::Shared) | (_, OutputEffects::Sends) => settings.output_count + 2,
+            _ => settings.output_count,
+        }
+    }
+
+    /// Gets the output of [`Synthesizer::render_multi`] which a channel is written to.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel.
+    pub fn get_channel_output(&self, channel: usize) -> Option<usize> {
+        self.channel_outputs.get(channel).copied()
+    }
+
+    /// Routes a channel to an output of [`Synthesizer::render_multi`].
+    /// Several channels can share an output to make a group.
+    /// This has no effect if the channel or the output is out of range.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel.
+    /// * `output` - The output, less than [`SynthesizerSettings::output_count`].
+    pub fn set_channel_output(&mut self, channel: usize, output: usize) {
+        if output >= self.settings.output_count {
+            return;
+        }
+
+        if let Some(channel_output) = self.channel_outputs.get_mut(channel) {
+            *channel_output = output;
+        }
+    }
+
+    /// Gets the parameters of the reverb, or `None` if reverb and chorus are disabled.
+    pub fn get_reverb_parameters(&self) -> Option<&ReverbParameters> {
+        self.effects
+            .first()
+            .map(|effects| effects.reverb.get_parameters())
+    }
+
+    /// Sets the parameters of the reverb.
+    /// This has no effect if reverb and chorus are disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the reverb.
+    pub fn set_reverb_parameters(&mut self, value: &ReverbParameters) {
+        for effects in self.effects.iter_mut() {
+            effects.reverb.set_parameters(value);
+        }
+    }
+
+    /// Sets the parameters of the reverb to a preset.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - The preset to apply.
+    pub fn set_reverb_preset(&mut self, preset: ReverbPreset) {
+        self.set_reverb_parameters(&preset.get_parameters());
+    }
+
+    /// Changes some of the reverb parameters, keeping the others.
+    fn update_reverb_parameters(&mut self, update: impl FnOnce(&mut ReverbParameters)) {
+        if let Some(&parameters) = self.get_reverb_parameters() {
+            let mut parameters = parameters;
+            update(&mut parameters);
+            self.set_reverb_parameters(&parameters);
+        }
+    }
+
+    /// Gets the parameters of the chorus, or `None` if reverb and chorus are disabled.
+    pub fn get_chorus_parameters(&self) -> Option<&ChorusParameters> {
+        self.effects
+            .first()
+            .map(|effects| effects.chorus.get_parameters())
+    }
+
+    /// Sets the parameters of the chorus.
+    /// The buffers are not reallocated, so this can be called while rendering.
+    /// This has no effect if reverb and chorus are disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the chorus.
+    pub fn set_chorus_parameters(&mut self, value: &ChorusParameters) {
+        for effects in self.effects.iter_mut() {
+            effects.chorus.set_parameters(value);
+        }
+    }
+
+    /// Sets the parameters of the chorus to a preset.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - The preset to apply.
+    pub fn set_chorus_preset(&mut self, preset: ChorusPreset) {
+        self.set_chorus_parameters(&preset.get_parameters());
+    }
+
+    /// Changes some of the chorus parameters, keeping the others.
+    fn update_chorus_parameters(&mut self, update: impl FnOnce(&mut ChorusParameters)) {
+        if let Some(&parameters) = self.get_chorus_parameters() {
+            let mut parameters = parameters;
+            update(&mut parameters);
+            self.set_chorus_parameters(&parameters);
+        }
+    }
+
+    /// Gets the parameters of the master equalizer.
+    pub fn get_equalizer_parameters(&self) -> &EqualizerParameters {
+        self.master.get_equalizer_parameters()
+    }
+
+    /// Sets the parameters of the master equalizer, which is bypassed unless enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the equalizer.
+    pub fn set_equalizer_parameters(&mut self, value: &EqualizerParameters) {
+        self.master.set_equalizer_parameters(value);
+    }
+
+    /// Gets the parameters of the master compressor.
+    pub fn get_compressor_parameters(&self) -> &CompressorParameters {
+        self.master.get_compressor_parameters()
+    }
+
+    /// Sets the parameters of the master compressor, which is bypassed unless enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the compressor.
+    pub fn set_compressor_parameters(&mut self, value: &CompressorParameters) {
+        self.master.set_compressor_parameters(value);
+    }
+
+    /// Gets the parameters of the master limiter.
+    pub fn get_limiter_parameters(&self) -> &LimiterParameters {
+        self.master.get_limiter_parameters()
+    }
+
+    /// Sets the parameters of the master limiter, which is bypassed unless enabled.
+    /// While enabled, the output is delayed by the look-ahead time.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the limiter.
+    pub fn set_limiter_parameters(&mut self, value: &LimiterParameters) {
+        self.master.set_limiter_parameters(value);
+    }
+
+    /// Gets the largest gain reduction of the limiter in the last rendered block, in dB.
+    pub fn get_limiter_gain_reduction(&self) -> f32 {
+        self.master.get_limiter_gain_reduction()
     }
 
     /// Gets the master volume.
@@ -604,6 +1710,41 @@ impl Synthesizer {
     pub fn set_master_volume(&mut self, value: f32) {
         self.master_volume = value;
     }
+
+    /// Gets the master tuning in cents.
+    pub fn get_master_tune(&self) -> f32 {
+        self.master_tune
+    }
+
+    /// Sets the master tuning in cents, which applies to every voice immediately.
+    /// It adds to the master tuning set by SysEx, and is kept across resets.
+    ///
+    /// # Arguments
+    ///
+    /// * `cents` - The new master tuning. For example, A4 at 442 Hz is about 7.85 cents.
+    pub fn set_master_tune(&mut self, cents: f32) {
+        self.master_tune = cents;
+    }
+
+    /// Gets the master transposition in semitones.
+    pub fn get_master_transpose(&self) -> i32 {
+        self.master_transpose
+    }
+
+    /// Sets the master transposition in semitones, which applies to every voice immediately.
+    /// As with the GS master key shift, percussion channels are not transposed.
+    ///
+    /// # Arguments
+    ///
+    /// * `semitones` - The new master transposition.
+    pub fn set_master_transpose(&mut self, semitones: i32) {
+        self.master_transpose = semitones;
+    }
+}
+
+enum VoiceSlot {
+    New,
+    Replace(usize),
 }
 
 struct Effects {
@@ -622,15 +1763,262 @@ struct Effects {
 impl Effects {
     fn new(settings: &SynthesizerSettings) -> Effects {
         Self {
-            reverb: Reverb::new(settings.sample_rate),
+            reverb: Reverb::new(
+                settings.sample_rate,
+                settings.block_size,
+                &settings.reverb_backend,
+            ),
             reverb_input: vec![0_f32; settings.block_size],
             reverb_output_left: vec![0_f32; settings.block_size],
             reverb_output_right: vec![0_f32; settings.block_size],
-            chorus: Chorus::new(settings.sample_rate, 0.002, 0.0019, 0.4),
+            chorus: Chorus::new(settings.sample_rate, &ChorusParameters::default()),
             chorus_input_left: vec![0_f32; settings.block_size],
             chorus_input_right: vec![0_f32; settings.block_size],
             chorus_output_left: vec![0_f32; settings.block_size],
             chorus_output_right: vec![0_f32; settings.block_size],
         }
     }
+
+    fn clear_sends(&mut self) {
+        self.reverb_input.fill(0_f32);
+        self.chorus_input_left.fill(0_f32);
+        self.chorus_input_right.fill(0_f32);
+    }
+
+    fn write_sends(&mut self, voice: &Voice, inverse_block_size: f32) {
+        Synthesizer::write_block(
+            voice.previous_chorus_send * voice.previous_mix_gain_left,
+            voice.current_chorus_send * voice.current_mix_gain_left,
+            &voice.block[..],
+            &mut self.chorus_input_left[..],
+            inverse_block_size,
+        );
+        Synthesizer::write_block(
+            voice.previous_chorus_send * voice.previous_mix_gain_right,
+            voice.current_chorus_send * voice.current_mix_gain_right,
+            &voice.block[..],
+            &mut self.chorus_input_right[..],
+            inverse_block_size,
+        );
+
+        let previous_gain = self.reverb.get_input_gain()
+            * voice.previous_reverb_send
+            * (voice.previous_mix_gain_left + voice.previous_mix_gain_right);
+        let current_gain = self.reverb.get_input_gain()
+            * voice.current_reverb_send
+            * (voice.current_mix_gain_left + voice.current_mix_gain_right);
+        Synthesizer::write_block(
+            previous_gain,
+            current_gain,
+            &voice.block[..],
+            &mut self.reverb_input[..],
+            inverse_block_size,
+        );
+    }
+
+    fn process(&mut self) {
+        self.chorus.process(
+            &self.chorus_input_left,
+            &self.chorus_input_right,
+            &mut self.chorus_output_left,
+            &mut self.chorus_output_right,
+        );
+        self.reverb.process_mono(
+            &mut self.reverb_input,
+            &mut self.reverb_output_left,
+            &mut self.reverb_output_right,
+        );
+    }
+
+    /// Mixes the outputs of the chorus and the reverb into the destination.
+    fn write_returns(&self, gain: f32, left: &mut [f32], right: &mut [f32]) {
+        ArrayMath::multiply_add(gain, &self.chorus_output_left, left);
+        ArrayMath::multiply_add(gain, &self.chorus_output_right, right);
+        ArrayMath::multiply_add(gain, &self.reverb_output_left, left);
+        ArrayMath::multiply_add(gain, &self.reverb_output_right, right);
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generators which differ from their defaults, so that each resolved parameter is used.
+    const GENERATORS: [(u16, i16); 33] = [
+        (5, 30),     // modLfoToPitch
+        (6, 20),     // vibLfoToPitch
+        (7, 200),    // modEnvToPitch
+        (8, 6000),   // initialFilterFc
+        (9, 60),     // initialFilterQ
+        (10, 300),   // modLfoToFilterFc
+        (11, -1200), // modEnvToFilterFc
+        (13, 30),    // modLfoToVolume
+        (17, -200),  // pan
+        (21, -2000), // delayModLFO
+        (22, 100),   // freqModLFO
+        (23, -3000), // delayVibLFO
+        (24, 200),   // freqVibLFO
+        (25, -5000), // delayModEnv
+        (26, -2000), // attackModEnv
+        (27, -4000), // holdModEnv
+        (28, -1000), // decayModEnv
+        (29, 300),   // sustainModEnv
+        (30, -1000), // releaseModEnv
+        (31, 10),    // keynumToModEnvHold
+        (32, 20),    // keynumToModEnvDecay
+        (33, -6000), // delayVolEnv
+        (34, -3000), // attackVolEnv
+        (35, -5000), // holdVolEnv
+        (36, -1000), // decayVolEnv
+        (37, 200),   // sustainVolEnv
+        (38, -1000), // releaseVolEnv
+        (39, 5),     // keynumToVolEnvHold
+        (40, 10),    // keynumToVolEnvDecay
+        (48, 60),    // initialAttenuation
+        (51, 1),     // coarseTune
+        (52, 10),    // fineTune
+        (56, 90),    // scaleTuning
+    ];
+
+    fn create_synthesizer() -> Synthesizer {
+        let data = common::create_sound_font_with_generators(&GENERATORS);
+        let sound_font = Arc::new(SoundFont::new(&mut &data[..]).unwrap());
+        let mut settings = SynthesizerSettings::new(48000);
+        settings.enable_reverb_and_chorus = false;
+        Synthesizer::new(sound_font, &settings).unwrap()
+    }
+
+    /// Sends a GS/XG NRPN with the MSB 0x01.
+    fn send_nrpn(synthesizer: &mut Synthesizer, lsb: u8, value: u8) {
+        synthesizer.process_channel_command(0, 0xB0, 0x63, 0x01);
+        synthesizer.process_channel_command(0, 0xB0, 0x62, lsb);
+        synthesizer.process_channel_command(0, 0xB0, 0x06, value);
+    }
+
+    /// Creates a voice the way it was created before the regions were cached,
+    /// by resolving the region with the offsets of the channel at note-on.
+    fn create_reference(
+        synthesizer: &Synthesizer,
+        key: u8,
+        glide_from: Option<(u8, f32)>,
+        soft_pedal: bool,
+    ) -> Voice {
+        let preset_region = &synthesizer.sound_font.presets[0].regions[0];
+        let instrument_region =
+            &synthesizer.sound_font.instruments[preset_region.instrument].regions[0];
+        let channel = &synthesizer.channels[0];
+        let offsets = channel.get_generator_offsets(key);
+        let region = ResolvedRegion::new(
+            &RegionPair::new(preset_region, instrument_region).with_offsets(&offsets),
+        );
+
+        let velocity = channel.get_note_velocity(utils::scale_up(100, 7, 16) as u16);
+        let block = vec![0_f32; synthesizer.get_block_size()];
+        let mut voice = Voice::new(&synthesizer.settings, &region, 0, key, key, velocity, block);
+        if let Some((from_key, time)) = glide_from {
+            voice.glide_from(from_key, time);
+        }
+        if soft_pedal {
+            voice.soften();
+        }
+        voice
+    }
+
+    /// Renders the synthesizer block by block, and checks that each of its voices
+    /// renders the same blocks as the corresponding reference voice.
+    fn assert_renders_like(synthesizer: &mut Synthesizer, references: &mut [Voice]) {
+        let block_size = synthesizer.get_block_size();
+        let mut left = vec![0_f32; block_size];
+        let mut right = vec![0_f32; block_size];
+
+        // Long enough for the envelopes, the LFOs and the glide to take effect.
+        for _ in 0..(48000 / block_size) {
+            synthesizer.render(&mut left, &mut right);
+            assert_eq!(synthesizer.voices.len(), references.len());
+
+            for (voice, reference) in synthesizer.voices.iter().zip(references.iter_mut()) {
+                assert!(reference.process(
+                    &synthesizer.sound_font.wave_data,
+                    &synthesizer.channels,
+                    0_f32,
+                    0_f32
+                ));
+                assert_eq!(voice.block, reference.block);
+            }
+        }
+    }
+
+    #[test]
+    fn cached_regions_render_like_regions_resolved_at_note_on() {
+        let mut synthesizer = create_synthesizer();
+        synthesizer.note_on(0, 60, 100);
+        let mut references = [create_reference(&synthesizer, 60, None, false)];
+
+        assert_renders_like(&mut synthesizer, &mut references);
+    }
+
+    #[test]
+    fn offsets_soft_pedal_and_glide_apply_on_top_of_the_region() {
+        let mut synthesizer = create_synthesizer();
+        synthesizer.note_on(0, 60, 100);
+        let mut references = vec![create_reference(&synthesizer, 60, None, false)];
+
+        // The NRPN cutoff and attack resolve the region again on the next note.
+        send_nrpn(&mut synthesizer, 0x20, 80);
+        send_nrpn(&mut synthesizer, 0x63, 40);
+        synthesizer.process_channel_command(0, 0xB0, 0x43, 127);
+        synthesizer.process_channel_command(0, 0xB0, 0x05, 20);
+        synthesizer.process_channel_command(0, 0xB0, 0x41, 127);
+        assert!(!synthesizer.channels[0].get_generator_offsets(64).is_zero());
+
+        synthesizer.note_on(0, 64, 100);
+        let glide_time = synthesizer.channels[0].get_portamento_time();
+        references.push(create_reference(
+            &synthesizer,
+            64,
+            Some((60, glide_time)),
+            true,
+        ));
+
+        assert_renders_like(&mut synthesizer, &mut references);
+    }
+
+    #[test]
+    fn offsets_soft_pedal_and_glide_change_the_output() {
+        let mut synthesizer = create_synthesizer();
+        let plain = create_reference(&synthesizer, 64, None, false);
+        let glided = create_reference(&synthesizer, 64, Some((60, 0.5_f32)), false);
+        let softened = create_reference(&synthesizer, 64, None, true);
+        send_nrpn(&mut synthesizer, 0x20, 80);
+        send_nrpn(&mut synthesizer, 0x63, 40);
+        let offset = create_reference(&synthesizer, 64, None, false);
+
+        let mut outputs = Vec::new();
+        for mut voice in [plain, glided, softened, offset] {
+            let mut output = Vec::new();
+            for _ in 0..100 {
+                voice.process(
+                    &synthesizer.sound_font.wave_data,
+                    &synthesizer.channels,
+                    0_f32,
+                    0_f32,
+                );
+                output.extend_from_slice(&voice.block);
+            }
+            outputs.push(output);
+        }
+
+        for i in 0..outputs.len() {
+            for j in i + 1..outputs.len() {
+                assert_ne!(
+                    outputs[i], outputs[j],
+                    "variants {i} and {j} render the same"
+                );
+            }
+        }
+    }
 }
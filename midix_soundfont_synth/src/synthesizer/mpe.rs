@@ -0,0 +1,109 @@
+use core::ops::RangeInclusive;
+
+/// A zone of the MPE (MIDI Polyphonic Expression) configuration.
+///
+/// A zone consists of a master channel, whose messages apply to the whole zone,
+/// and member channels, each of which carries a single note with its own
+/// pitch bend, pressure and timbre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MpeZone {
+    master_channel: u8,
+    member_channel_count: u8,
+}
+
+impl MpeZone {
+    /// The master channel of the lower zone.
+    pub const LOWER_MASTER_CHANNEL: u8 = 0;
+    /// The master channel of the upper zone.
+    pub const UPPER_MASTER_CHANNEL: u8 = 15;
+
+    /// The default pitch bend range of the member channels in semitones.
+    pub const DEFAULT_MEMBER_PITCH_BEND_RANGE: u8 = 48;
+    /// The default pitch bend range of the master channel in semitones.
+    pub const DEFAULT_MASTER_PITCH_BEND_RANGE: u8 = 2;
+
+    /// Gets the master channel of the zone.
+    pub fn get_master_channel(&self) -> u8 {
+        self.master_channel
+    }
+
+    /// Gets the number of member channels in the zone.
+    pub fn get_member_channel_count(&self) -> u8 {
+        self.member_channel_count
+    }
+
+    /// Gets the member channels of the zone.
+    pub fn get_member_channels(&self) -> RangeInclusive<u8> {
+        if self.master_channel == MpeZone::LOWER_MASTER_CHANNEL {
+            1..=self.member_channel_count
+        } else {
+            (MpeZone::UPPER_MASTER_CHANNEL - self.member_channel_count)
+                ..=(MpeZone::UPPER_MASTER_CHANNEL - 1)
+        }
+    }
+
+    /// Gets the value indicating whether the channel belongs to the zone.
+    pub fn contains(&self, channel: u8) -> bool {
+        channel == self.master_channel || self.get_member_channels().contains(&channel)
+    }
+}
+
+/// The lower and upper zones, as set up by the MPE configuration message (RPN 6).
+#[derive(Default)]
+pub(crate) struct MpeConfiguration {
+    lower_member_count: u8,
+    upper_member_count: u8,
+}
+
+impl MpeConfiguration {
+    const MAXIMUM_MEMBER_COUNT: u8 = 15;
+
+    /// Sets the number of member channels of the zone whose master channel is given.
+    /// A zone which would overlap with the new one is shrunk, or turned off if nothing is left.
+    ///
+    /// Returns `false` if the channel cannot be the master channel of a zone.
+    pub(crate) fn set_zone(&mut self, master_channel: u8, member_channel_count: u8) -> bool {
+        let count = member_channel_count.min(MpeConfiguration::MAXIMUM_MEMBER_COUNT);
+
+        match master_channel {
+            MpeZone::LOWER_MASTER_CHANNEL => {
+                self.lower_member_count = count;
+                self.upper_member_count = self.upper_member_count.min(14_u8.saturating_sub(count));
+            }
+            MpeZone::UPPER_MASTER_CHANNEL => {
+                self.upper_member_count = count;
+                self.lower_member_count = self.lower_member_count.min(14_u8.saturating_sub(count));
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lower_member_count = 0;
+        self.upper_member_count = 0;
+    }
+
+    pub(crate) fn get_lower_zone(&self) -> Option<MpeZone> {
+        (self.lower_member_count > 0).then_some(MpeZone {
+            master_channel: MpeZone::LOWER_MASTER_CHANNEL,
+            member_channel_count: self.lower_member_count,
+        })
+    }
+
+    pub(crate) fn get_upper_zone(&self) -> Option<MpeZone> {
+        (self.upper_member_count > 0).then_some(MpeZone {
+            master_channel: MpeZone::UPPER_MASTER_CHANNEL,
+            member_channel_count: self.upper_member_count,
+        })
+    }
+
+    /// Gets the zone in which the channel is a member channel.
+    pub(crate) fn get_zone_of_member(&self, channel: u8) -> Option<MpeZone> {
+        self.get_lower_zone()
+            .into_iter()
+            .chain(self.get_upper_zone())
+            .find(|zone| zone.get_member_channels().contains(&channel))
+    }
+}
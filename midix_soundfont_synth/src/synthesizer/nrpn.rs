@@ -0,0 +1,177 @@
+use crate::{prelude::*, utils};
+
+use super::voice::GeneratorOffsets;
+
+/// The GS/XG NRPN parameters of a channel.
+///
+/// The values are stored as received, with 64 as the center for the relative ones.
+#[derive(Clone, Copy)]
+pub(crate) struct NrpnParameters {
+    vibrato_rate: u8,
+    vibrato_depth: u8,
+    vibrato_delay: u8,
+    cutoff: u8,
+    resonance: u8,
+    attack: u8,
+    decay: u8,
+    release: u8,
+    drum_notes: [DrumNoteParameters; 128],
+}
+
+/// The GS/XG NRPN parameters of a single note of a drum channel.
+#[derive(Clone, Copy)]
+struct DrumNoteParameters {
+    pitch: u8,
+    level: u8,
+    pan: u8,
+    reverb: u8,
+    chorus: u8,
+}
+
+impl NrpnParameters {
+    const VIBRATO_RATE: u16 = (0x01 << 7) | 0x08;
+    const VIBRATO_DEPTH: u16 = (0x01 << 7) | 0x09;
+    const VIBRATO_DELAY: u16 = (0x01 << 7) | 0x0A;
+    const CUTOFF: u16 = (0x01 << 7) | 0x20;
+    const RESONANCE: u16 = (0x01 << 7) | 0x21;
+    const ATTACK: u16 = (0x01 << 7) | 0x63;
+    const DECAY: u16 = (0x01 << 7) | 0x64;
+    const RELEASE: u16 = (0x01 << 7) | 0x66;
+
+    const DRUM_PITCH: u16 = 0x18;
+    const DRUM_LEVEL: u16 = 0x1A;
+    const DRUM_PAN: u16 = 0x1C;
+    const DRUM_REVERB: u16 = 0x1D;
+    const DRUM_CHORUS: u16 = 0x1E;
+
+    // The amount of change per step of the relative parameters, in the units of the generators.
+    const RATE_STEP: i32 = 25; // cents
+    const DEPTH_STEP: i32 = 4; // cents
+    const TIME_STEP: i32 = 80; // timecents
+    const CUTOFF_STEP: i32 = 50; // cents
+    const RESONANCE_STEP: i32 = 3; // centibels
+
+    pub(crate) const DEFAULT: NrpnParameters = NrpnParameters {
+        vibrato_rate: 64,
+        vibrato_depth: 64,
+        vibrato_delay: 64,
+        cutoff: 64,
+        resonance: 64,
+        attack: 64,
+        decay: 64,
+        release: 64,
+        drum_notes: [DrumNoteParameters::DEFAULT; 128],
+    };
+
+    /// Stores the value of the NRPN. Unknown NRPNs are ignored.
+    pub(crate) fn set(&mut self, nrpn: u16, value: u8) {
+        let parameter = match nrpn {
+            NrpnParameters::VIBRATO_RATE => &mut self.vibrato_rate,
+            NrpnParameters::VIBRATO_DEPTH => &mut self.vibrato_depth,
+            NrpnParameters::VIBRATO_DELAY => &mut self.vibrato_delay,
+            NrpnParameters::CUTOFF => &mut self.cutoff,
+            NrpnParameters::RESONANCE => &mut self.resonance,
+            NrpnParameters::ATTACK => &mut self.attack,
+            NrpnParameters::DECAY => &mut self.decay,
+            NrpnParameters::RELEASE => &mut self.release,
+            _ => {
+                // The per-note drum parameters have the key in the LSB.
+                let note = &mut self.drum_notes[(nrpn & 0x7F) as usize];
+                match nrpn >> 7 {
+                    NrpnParameters::DRUM_PITCH => &mut note.pitch,
+                    NrpnParameters::DRUM_LEVEL => &mut note.level,
+                    NrpnParameters::DRUM_PAN => &mut note.pan,
+                    NrpnParameters::DRUM_REVERB => &mut note.reverb,
+                    NrpnParameters::DRUM_CHORUS => &mut note.chorus,
+                    _ => return,
+                }
+            }
+        };
+
+        *parameter = value;
+    }
+
+    /// Gets the generator offsets for a note.
+    /// The drum parameters only apply to percussion channels.
+    pub(crate) fn get_generator_offsets(&self, key: u8, is_percussion: bool) -> GeneratorOffsets {
+        let mut offsets = GeneratorOffsets::ZERO;
+
+        let relative = |value: u8, step: i32| (value as i32 - 64) * step;
+        offsets.add(
+            GeneratorType::FREQUENCY_VIBRATO_LFO,
+            relative(self.vibrato_rate, NrpnParameters::RATE_STEP),
+        );
+        offsets.add(
+            GeneratorType::VIBRATO_LFO_TO_PITCH,
+            relative(self.vibrato_depth, NrpnParameters::DEPTH_STEP),
+        );
+        offsets.add(
+            GeneratorType::DELAY_VIBRATO_LFO,
+            relative(self.vibrato_delay, NrpnParameters::TIME_STEP),
+        );
+        offsets.add(
+            GeneratorType::INITIAL_FILTER_CUTOFF_FREQUENCY,
+            relative(self.cutoff, NrpnParameters::CUTOFF_STEP),
+        );
+        offsets.add(
+            GeneratorType::INITIAL_FILTER_Q,
+            relative(self.resonance, NrpnParameters::RESONANCE_STEP),
+        );
+        offsets.add(
+            GeneratorType::ATTACK_VOLUME_ENVELOPE,
+            relative(self.attack, NrpnParameters::TIME_STEP),
+        );
+        offsets.add(
+            GeneratorType::DECAY_VOLUME_ENVELOPE,
+            relative(self.decay, NrpnParameters::TIME_STEP),
+        );
+        offsets.add(
+            GeneratorType::RELEASE_VOLUME_ENVELOPE,
+            relative(self.release, NrpnParameters::TIME_STEP),
+        );
+
+        if is_percussion {
+            self.drum_notes[key as usize & 0x7F].add_offsets(&mut offsets);
+        }
+
+        offsets
+    }
+}
+
+impl DrumNoteParameters {
+    const DEFAULT: DrumNoteParameters = DrumNoteParameters {
+        pitch: 64,
+        level: 127,
+        pan: 64,
+        reverb: 127,
+        chorus: 127,
+    };
+
+    fn add_offsets(&self, offsets: &mut GeneratorOffsets) {
+        offsets.add(GeneratorType::COARSE_TUNE, self.pitch as i32 - 64);
+
+        // The voice only applies 40% of the initial attenuation, so it is compensated here.
+        if self.level < 127 {
+            let decibels = -utils::linear_to_decibels(self.level.max(1) as f32 / 127_f32);
+            offsets.add(
+                GeneratorType::INITIAL_ATTENUATION,
+                (25_f32 * decibels) as i32,
+            );
+        }
+
+        // Zero means random in GS, which is treated as the center.
+        if self.pan > 0 {
+            offsets.add(GeneratorType::PAN, (self.pan as i32 - 64) * 1000 / 127);
+        }
+
+        // The sends scale down from the full amount at 127, in 0.1% units.
+        offsets.add(
+            GeneratorType::REVERB_EFFECTS_SEND,
+            (self.reverb as i32 - 127) * 1000 / 127,
+        );
+        offsets.add(
+            GeneratorType::CHORUS_EFFECTS_SEND,
+            (self.chorus as i32 - 127) * 1000 / 127,
+        );
+    }
+}
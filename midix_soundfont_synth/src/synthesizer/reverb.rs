@@ -1,188 +1,362 @@
-use core::cmp;
+use std::sync::Arc;
 
 use bevy_platform::prelude::*;
-pub(crate) struct Reverb {
-    left_combfilters: Vec<CombFilter>,
-    left_allpassfilters: Vec<AllPassFilter>,
-
-    right_combfilters: Vec<CombFilter>,
-    right_allpassfilters: Vec<AllPassFilter>,
-
-    gain: f32,
-    room_size: f32,
-    room_size1: f32,
-    damp: f32,
-    damp1: f32,
-    wet: f32,
-    wet1: f32,
-    wet2: f32,
-    width: f32,
+
+use super::{ConvolutionReverb, FeedbackDelayNetwork, Freeverb, ImpulseResponse};
+
+/// The reverb engine behind the reverb send.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ReverbBackend {
+    /// The Freeverb comb and all-pass filter network.
+    #[default]
+    Freeverb,
+    /// A partitioned FFT convolution with an impulse response.
+    /// The room size and damping parameters have no effect on this backend.
+    Convolution(Arc<ImpulseResponse>),
+    /// A feedback delay network with 8 modulated delay lines, which is cheap enough for mobile targets.
+    Fdn8,
+    /// A feedback delay network with 16 modulated delay lines, which has a denser tail.
+    Fdn16,
 }
 
-impl Reverb {
-    const FIXED_GAIN: f32 = 0.015;
-    const SCALE_WET: f32 = 3.0;
-    const SCALE_DAMP: f32 = 0.4;
-    const SCALE_ROOM: f32 = 0.28;
-    const OFFSET_ROOM: f32 = 0.7;
-    const INITIAL_ROOM: f32 = 0.5;
-    const INITIAL_DAMP: f32 = 0.5;
-    const INITIAL_WET: f32 = 1.0 / Reverb::SCALE_WET;
-    const INITIAL_WIDTH: f32 = 1.0;
-    const STEREO_SPREAD: usize = 23;
-
-    const CF_TUNING_L1: usize = 1116;
-    const CF_TUNING_R1: usize = 1116 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L2: usize = 1188;
-    const CF_TUNING_R2: usize = 1188 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L3: usize = 1277;
-    const CF_TUNING_R3: usize = 1277 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L4: usize = 1356;
-    const CF_TUNING_R4: usize = 1356 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L5: usize = 1422;
-    const CF_TUNING_R5: usize = 1422 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L6: usize = 1491;
-    const CF_TUNING_R6: usize = 1491 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L7: usize = 1557;
-    const CF_TUNING_R7: usize = 1557 + Reverb::STEREO_SPREAD;
-    const CF_TUNING_L8: usize = 1617;
-    const CF_TUNING_R8: usize = 1617 + Reverb::STEREO_SPREAD;
-    const APF_TUNING_L1: usize = 556;
-    const APF_TUNING_R1: usize = 556 + Reverb::STEREO_SPREAD;
-    const APF_TUNING_L2: usize = 441;
-    const APF_TUNING_R2: usize = 441 + Reverb::STEREO_SPREAD;
-    const APF_TUNING_L3: usize = 341;
-    const APF_TUNING_R3: usize = 341 + Reverb::STEREO_SPREAD;
-    const APF_TUNING_L4: usize = 225;
-    const APF_TUNING_R4: usize = 225 + Reverb::STEREO_SPREAD;
-
-    pub(crate) fn new(sample_rate: i32) -> Self {
-        let left_combfilters: Vec<CombFilter> = vec![
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L1)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L2)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L3)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L4)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L5)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L6)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L7)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_L8)),
-        ];
+/// The parameters of the reverb.
+///
+/// # Remarks
+///
+/// `decay_time` was added for the FDN backends, which breaks the code building the parameters
+/// with a struct literal. Filling the remaining fields with `..ReverbParameters::default()`
+/// keeps such code working when more fields are added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReverbParameters {
+    /// The size of the room, from 0 to 1. Larger rooms have longer tails.
+    pub room_size: f32,
+    /// The time for the reverb to decay by 60 dB, in seconds.
+    /// This is used by the FDN backends, where the room size only sets the spacing of the echoes.
+    pub decay_time: f32,
+    /// The damping of the high frequencies, from 0 to 1.
+    pub damp: f32,
+    /// The stereo width, from 0 (mono) to 1.
+    pub width: f32,
+    /// The level of the reverb, from 0 to 1.
+    pub wet: f32,
+    /// The delay before the reverb starts, in seconds, up to [`ReverbParameters::MAXIMUM_PRE_DELAY`].
+    pub pre_delay: f32,
+}
 
-        let right_combfilters: Vec<CombFilter> = vec![
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R1)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R2)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R3)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R4)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R5)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R6)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R7)),
-            CombFilter::new(Reverb::scale_tuning(sample_rate, Reverb::CF_TUNING_R8)),
-        ];
+impl Default for ReverbParameters {
+    fn default() -> Self {
+        Self {
+            room_size: 0.5,
+            decay_time: 1.5,
+            damp: 0.5,
+            width: 1.0,
+            wet: 1.0 / 3.0,
+            pre_delay: 0.0,
+        }
+    }
+}
 
-        let mut left_allpassfilters: Vec<AllPassFilter> = vec![
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_L1)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_L2)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_L3)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_L4)),
-        ];
+impl ReverbParameters {
+    /// The maximum pre-delay in seconds.
+    pub const MAXIMUM_PRE_DELAY: f32 = 0.2;
+    /// The minimum decay time in seconds.
+    pub const MINIMUM_DECAY_TIME: f32 = 0.1;
+    /// The maximum decay time in seconds.
+    pub const MAXIMUM_DECAY_TIME: f32 = 20.0;
+
+    /// Sets the room size and the decay time from a GS reverb time value,
+    /// which goes from about 0.2 seconds at 0 to 10 seconds at 127.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The GS reverb time, from 0 to 127.
+    pub fn set_gs_time(&mut self, value: u8) {
+        let value = (1_f32 / 127_f32) * value.min(127) as f32;
+        self.room_size = value;
+        self.decay_time = 0.2_f32 * 50_f32.powf(value);
+    }
 
-        let mut right_allpassfilters: Vec<AllPassFilter> = vec![
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_R1)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_R2)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_R3)),
-            AllPassFilter::new(Reverb::scale_tuning(sample_rate, Reverb::APF_TUNING_R4)),
-        ];
+    /// Sets the room size and the damping from a GS reverb character value,
+    /// keeping the decay time. The delay characters (6 and 7) have no equivalent.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The GS reverb character, from 0 to 7.
+    pub fn set_gs_character(&mut self, value: u8) {
+        if let Some(preset) = ReverbPreset::from_gs_macro(value) {
+            let parameters = preset.get_parameters();
+            self.room_size = parameters.room_size;
+            self.damp = parameters.damp;
+        }
+    }
+}
+
+/// The named reverb presets, which include the GS reverb macros and the XG reverb types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReverbPreset {
+    Room1,
+    Room2,
+    Room3,
+    Hall1,
+    Hall2,
+    Plate,
+    Stage1,
+    Stage2,
+}
+
+impl ReverbPreset {
+    /// Gets the reverb parameters of the preset.
+    pub fn get_parameters(&self) -> ReverbParameters {
+        let (room_size, decay_time, damp, wet, pre_delay) = match self {
+            ReverbPreset::Room1 => (0.4, 0.6, 0.6, 0.25, 0.005),
+            ReverbPreset::Room2 => (0.5, 0.9, 0.5, 0.3, 0.008),
+            ReverbPreset::Room3 => (0.6, 1.2, 0.4, 0.3, 0.01),
+            ReverbPreset::Hall1 => (0.75, 1.8, 0.4, 0.33, 0.02),
+            ReverbPreset::Hall2 => (0.85, 2.5, 0.3, 0.33, 0.025),
+            ReverbPreset::Plate => (0.7, 1.6, 0.1, 0.33, 0.0),
+            ReverbPreset::Stage1 => (0.75, 2.0, 0.5, 0.3, 0.012),
+            ReverbPreset::Stage2 => (0.8, 2.2, 0.45, 0.3, 0.015),
+        };
 
-        for apf in left_allpassfilters.iter_mut() {
-            apf.set_feedback(0.5_f32);
+        ReverbParameters {
+            room_size,
+            decay_time,
+            damp,
+            width: 1.0,
+            wet,
+            pre_delay,
         }
+    }
 
-        for apf in right_allpassfilters.iter_mut() {
-            apf.set_feedback(0.5_f32);
+    /// Gets the preset of a GS reverb macro.
+    /// The delay macros (6 and 7) have no equivalent.
+    pub fn from_gs_macro(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ReverbPreset::Room1),
+            1 => Some(ReverbPreset::Room2),
+            2 => Some(ReverbPreset::Room3),
+            3 => Some(ReverbPreset::Hall1),
+            4 => Some(ReverbPreset::Hall2),
+            5 => Some(ReverbPreset::Plate),
+            _ => None,
         }
+    }
+
+    /// Gets the preset of an XG reverb type, given as its MSB and LSB.
+    /// Type 0 (no effect) and the unsupported types have no equivalent.
+    pub fn from_xg_type(msb: u8, lsb: u8) -> Option<Self> {
+        match (msb, lsb) {
+            (0x01, 0x00) => Some(ReverbPreset::Hall1),
+            (0x01, _) => Some(ReverbPreset::Hall2),
+            (0x02, 0x00) => Some(ReverbPreset::Room1),
+            (0x02, 0x01) => Some(ReverbPreset::Room2),
+            (0x02, _) => Some(ReverbPreset::Room3),
+            (0x03, 0x00) => Some(ReverbPreset::Stage1),
+            (0x03, _) => Some(ReverbPreset::Stage2),
+            (0x04, _) => Some(ReverbPreset::Plate),
+            _ => None,
+        }
+    }
+}
+
+/// A stereo reverb, which is the one behind the reverb send of the synthesizer.
+/// Only the reverberated signal is output, so the dry signal has to be mixed by the caller.
+pub struct Reverb {
+    engine: ReverbEngine,
+
+    wet1: f32,
+    wet2: f32,
+
+    parameters: ReverbParameters,
+
+    sample_rate: i32,
+    pre_delay_buffer: Vec<f32>,
+    pre_delay_index: usize,
+    pre_delay_length: usize,
+}
+
+enum ReverbEngine {
+    Freeverb(Freeverb),
+    Convolution(ConvolutionReverb),
+    Fdn8(Box<FeedbackDelayNetwork<8>>),
+    Fdn16(Box<FeedbackDelayNetwork<16>>),
+}
+
+impl Reverb {
+    const SCALE_WET: f32 = 3.0;
+
+    // The stereo input is mixed down in chunks of this size.
+    const CHUNK_SIZE: usize = 64;
+
+    /// Initializes a new reverb with the default parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate of the signal.
+    /// * `block_size` - The usual number of samples per call to process,
+    ///   which sets the latency of the convolution backend.
+    /// * `backend` - The reverb engine.
+    pub fn new(sample_rate: i32, block_size: usize, backend: &ReverbBackend) -> Self {
+        let engine = match backend {
+            ReverbBackend::Freeverb => ReverbEngine::Freeverb(Freeverb::new(sample_rate)),
+            ReverbBackend::Convolution(impulse_response) => ReverbEngine::Convolution(
+                ConvolutionReverb::new(sample_rate, block_size, impulse_response),
+            ),
+            ReverbBackend::Fdn8 => {
+                ReverbEngine::Fdn8(Box::new(FeedbackDelayNetwork::new(sample_rate)))
+            }
+            ReverbBackend::Fdn16 => {
+                ReverbEngine::Fdn16(Box::new(FeedbackDelayNetwork::new(sample_rate)))
+            }
+        };
 
         let mut reverb = Reverb {
-            left_combfilters,
-            right_combfilters,
-            left_allpassfilters,
-            right_allpassfilters,
-            gain: 0_f32,
-            room_size: 0_f32,
-            room_size1: 0_f32,
-            damp: 0_f32,
-            damp1: 0_f32,
-            wet: 0_f32,
+            engine,
             wet1: 0_f32,
             wet2: 0_f32,
-            width: 0_f32,
+            parameters: ReverbParameters::default(),
+            sample_rate,
+            // One more sample than the delay, as the input is written before the output is read.
+            pre_delay_buffer: vec![
+                0_f32;
+                (sample_rate as f32 * ReverbParameters::MAXIMUM_PRE_DELAY)
+                    as usize
+                    + 1
+            ],
+            pre_delay_index: 0,
+            pre_delay_length: 0,
         };
 
-        reverb.set_wet(Reverb::INITIAL_WET);
-        reverb.set_room_size(Reverb::INITIAL_ROOM);
-        reverb.set_damp(Reverb::INITIAL_DAMP);
-        reverb.set_width(Reverb::INITIAL_WIDTH);
+        reverb.set_parameters(&ReverbParameters::default());
 
         reverb
     }
 
-    pub fn mute(&mut self) {
-        for cf in self.left_combfilters.iter_mut() {
-            cf.mute();
-        }
+    /// Gets the parameters of the reverb.
+    pub fn get_parameters(&self) -> &ReverbParameters {
+        &self.parameters
+    }
 
-        for cf in self.right_combfilters.iter_mut() {
-            cf.mute();
-        }
+    /// Sets the parameters of the reverb, without clearing the sound which is reverberating.
+    /// The values out of range are clamped.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new parameters of the reverb.
+    pub fn set_parameters(&mut self, value: &ReverbParameters) {
+        self.parameters = ReverbParameters {
+            room_size: value.room_size.clamp(0_f32, 1_f32),
+            decay_time: value.decay_time.clamp(
+                ReverbParameters::MINIMUM_DECAY_TIME,
+                ReverbParameters::MAXIMUM_DECAY_TIME,
+            ),
+            damp: value.damp.clamp(0_f32, 1_f32),
+            width: value.width.clamp(0_f32, 1_f32),
+            wet: value.wet.clamp(0_f32, 1_f32),
+            pre_delay: value
+                .pre_delay
+                .clamp(0_f32, ReverbParameters::MAXIMUM_PRE_DELAY),
+        };
 
-        for apf in self.left_allpassfilters.iter_mut() {
-            apf.mute();
+        let wet = self.parameters.wet * Reverb::SCALE_WET;
+        let width = self.parameters.width;
+        self.wet1 = wet * (width / 2_f32 + 0.5_f32);
+        self.wet2 = wet * ((1_f32 - width) / 2_f32);
+
+        match &mut self.engine {
+            ReverbEngine::Freeverb(freeverb) => freeverb.set_parameters(&self.parameters),
+            ReverbEngine::Convolution(_) => (),
+            ReverbEngine::Fdn8(fdn) => fdn.set_parameters(&self.parameters),
+            ReverbEngine::Fdn16(fdn) => fdn.set_parameters(&self.parameters),
         }
 
-        for apf in self.right_allpassfilters.iter_mut() {
-            apf.mute();
+        let pre_delay_length = ((self.sample_rate as f32 * self.parameters.pre_delay) as usize)
+            .min(self.pre_delay_buffer.len() - 1);
+        // The buffer is not written while the pre-delay is off, so it may hold an old signal.
+        if self.pre_delay_length == 0 && pre_delay_length > 0 {
+            self.pre_delay_buffer.fill(0_f32);
         }
+        self.pre_delay_length = pre_delay_length;
     }
 
-    fn scale_tuning(sample_rate: i32, tuning: usize) -> usize {
-        ((sample_rate as f64) / 44100_f64 * (tuning as f64)).round() as usize
+    /// Clears the sound which is reverberating.
+    pub fn mute(&mut self) {
+        match &mut self.engine {
+            ReverbEngine::Freeverb(freeverb) => freeverb.mute(),
+            ReverbEngine::Convolution(convolution) => convolution.mute(),
+            ReverbEngine::Fdn8(fdn) => fdn.mute(),
+            ReverbEngine::Fdn16(fdn) => fdn.mute(),
+        }
+
+        self.pre_delay_buffer.fill(0_f32);
     }
 
-    pub(crate) fn process(
+    /// Processes a stereo signal, which is mixed down to mono before the reverb.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_left` - The left channel of the input.
+    /// * `input_right` - The right channel of the input.
+    /// * `output_left` - The buffer to store the left channel of the reverb.
+    /// * `output_right` - The buffer to store the right channel of the reverb.
+    ///
+    /// # Remarks
+    ///
+    /// All the buffers must be the same length.
+    pub fn process(
         &mut self,
-        input: &[f32],
+        input_left: &[f32],
+        input_right: &[f32],
         output_left: &mut [f32],
         output_right: &mut [f32],
     ) {
-        let input_length = input.len();
-        let output_left_length = output_left.len();
-        let output_right_length = output_right.len();
-
-        for lsample in output_left.iter_mut().take(output_left_length) {
-            *lsample = 0_f32;
-        }
-        for rsample in output_right.iter_mut().take(output_right_length) {
-            *rsample = 0_f32;
-        }
-
-        for cf in self.left_combfilters.iter_mut() {
-            cf.process(input, output_left);
+        if input_left.len() != output_left.len()
+            || input_right.len() != output_left.len()
+            || output_right.len() != output_left.len()
+        {
+            panic!("The input and output buffers must be the same length.");
         }
 
-        for apf in self.left_allpassfilters.iter_mut() {
-            apf.process(output_left);
+        let input_gain = self.get_input_gain();
+        let mut input = [0_f32; Reverb::CHUNK_SIZE];
+
+        let chunks = input_left
+            .chunks(Reverb::CHUNK_SIZE)
+            .zip(input_right.chunks(Reverb::CHUNK_SIZE))
+            .zip(output_left.chunks_mut(Reverb::CHUNK_SIZE))
+            .zip(output_right.chunks_mut(Reverb::CHUNK_SIZE));
+        for (((input_left, input_right), output_left), output_right) in chunks {
+            let input = &mut input[..input_left.len()];
+            for ((value, &left), &right) in input.iter_mut().zip(input_left).zip(input_right) {
+                *value = input_gain * (left + right);
+            }
+            self.process_mono(input, output_left, output_right);
         }
+    }
 
-        for cf in self.right_combfilters.iter_mut() {
-            cf.process(input, output_right);
-        }
+    /// Processes a block of the reverb send, which is already scaled by the input gain.
+    /// The input is replaced with the pre-delayed input.
+    pub(crate) fn process_mono(
+        &mut self,
+        input: &mut [f32],
+        output_left: &mut [f32],
+        output_right: &mut [f32],
+    ) {
+        self.process_pre_delay(input);
+        let input = &*input;
 
-        for apf in self.right_allpassfilters.iter_mut() {
-            apf.process(output_right);
+        match &mut self.engine {
+            ReverbEngine::Freeverb(freeverb) => freeverb.process(input, output_left, output_right),
+            ReverbEngine::Convolution(convolution) => {
+                convolution.process(input, output_left, output_right)
+            }
+            ReverbEngine::Fdn8(fdn) => fdn.process(input, output_left, output_right),
+            ReverbEngine::Fdn16(fdn) => fdn.process(input, output_left, output_right),
         }
 
         // With the default settings, we can skip this part.
-        if 1_f32 - self.wet1 > 1.0E-3_f32 || self.wet2 > 1.0E-3_f32 {
-            for t in 0..input_length {
+        if (1_f32 - self.wet1).abs() > 1.0E-3_f32 || self.wet2 > 1.0E-3_f32 {
+            for t in 0..input.len() {
                 let left = output_left[t];
                 let right = output_right[t];
                 output_left[t] = left * self.wet1 + right * self.wet2;
@@ -191,195 +365,129 @@ impl Reverb {
         }
     }
 
-    fn update(&mut self) {
-        self.wet1 = self.wet * (self.width / 2_f32 + 0.5_f32);
-        self.wet2 = self.wet * ((1_f32 - self.width) / 2_f32);
-
-        self.room_size1 = self.room_size;
-        self.damp1 = self.damp;
-        self.gain = Reverb::FIXED_GAIN;
-
-        for cf in self.left_combfilters.iter_mut() {
-            cf.set_feedback(self.room_size1);
-            cf.set_damp(self.damp1);
+    fn process_pre_delay(&mut self, block: &mut [f32]) {
+        if self.pre_delay_length == 0 {
+            return;
         }
 
-        for cf in self.right_combfilters.iter_mut() {
-            cf.set_feedback(self.room_size1);
-            cf.set_damp(self.damp1);
-        }
-    }
-
-    pub fn get_input_gain(&self) -> f32 {
-        self.gain
-    }
-
-    fn set_room_size(&mut self, value: f32) {
-        self.room_size = (value * Reverb::SCALE_ROOM) + Reverb::OFFSET_ROOM;
-        self.update();
-    }
-
-    fn set_damp(&mut self, value: f32) {
-        self.damp = value * Reverb::SCALE_DAMP;
-        self.update();
-    }
-
-    fn set_wet(&mut self, value: f32) {
-        self.wet = value * Reverb::SCALE_WET;
-        self.update();
-    }
-
-    fn set_width(&mut self, value: f32) {
-        self.width = value;
-        self.update();
-    }
-}
-
-struct CombFilter {
-    buffer: Vec<f32>,
+        let buffer_length = self.pre_delay_buffer.len();
+        for sample in block.iter_mut() {
+            self.pre_delay_buffer[self.pre_delay_index] = *sample;
 
-    buffer_index: usize,
-    filter_store: f32,
-
-    feedback: f32,
-    damp1: f32,
-    damp2: f32,
-}
+            let read_index =
+                (self.pre_delay_index + buffer_length - self.pre_delay_length) % buffer_length;
+            *sample = self.pre_delay_buffer[read_index];
 
-impl CombFilter {
-    fn new(buffer_size: usize) -> Self {
-        Self {
-            buffer: vec![0_f32; buffer_size],
-            buffer_index: 0,
-            filter_store: 0_f32,
-            feedback: 0_f32,
-            damp1: 0_f32,
-            damp2: 0_f32,
-        }
-    }
-
-    fn mute(&mut self) {
-        let buffer_length = self.buffer.len();
-        for i in 0..buffer_length {
-            self.buffer[i] = 0_f32;
+            self.pre_delay_index += 1;
+            if self.pre_delay_index == buffer_length {
+                self.pre_delay_index = 0;
+            }
         }
-
-        self.filter_store = 0_f32;
     }
 
-    fn process(&mut self, input_block: &[f32], output_block: &mut [f32]) {
-        let buffer_length = self.buffer.len();
-        let output_block_length = output_block.len();
-
-        let mut block_index: usize = 0;
-        while block_index < output_block_length {
-            if self.buffer_index == buffer_length {
-                self.buffer_index = 0;
-            }
-
-            let src_rem = buffer_length - self.buffer_index;
-            let dst_rem = output_block_length - block_index;
-            let rem = cmp::min(src_rem, dst_rem);
-
-            for t in 0..rem {
-                let block_pos = block_index + t;
-                let buffer_pos = self.buffer_index + t;
-
-                let input = input_block[block_pos];
-
-                // The following ifs are to avoid performance problem due to denormalized number.
-                // The original implementation uses unsafe cast to detect denormalized number.
-                // I tried to reproduce the original implementation using Unsafe.As,
-                // but the simple Math.Abs version was faster according to some benchmarks.
-
-                let mut output = self.buffer[buffer_pos];
-                if output.abs() < 1.0E-6_f32 {
-                    output = 0_f32;
-                }
-
-                self.filter_store = (output * self.damp2) + (self.filter_store * self.damp1);
-                if self.filter_store.abs() < 1.0E-6_f32 {
-                    self.filter_store = 0_f32;
-                }
-
-                self.buffer[buffer_pos] = input + (self.filter_store * self.feedback);
-                output_block[block_pos] += output;
-            }
-
-            self.buffer_index += rem;
-            block_index += rem;
+    /// Gets the gain applied to the input, which depends on the backend.
+    pub(crate) fn get_input_gain(&self) -> f32 {
+        match &self.engine {
+            ReverbEngine::Freeverb(_) => Freeverb::INPUT_GAIN,
+            ReverbEngine::Convolution(_) => ConvolutionReverb::INPUT_GAIN,
+            ReverbEngine::Fdn8(_) => FeedbackDelayNetwork::<8>::INPUT_GAIN,
+            ReverbEngine::Fdn16(_) => FeedbackDelayNetwork::<16>::INPUT_GAIN,
         }
     }
+}
 
-    const fn set_feedback(&mut self, value: f32) {
-        self.feedback = value;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_energy(wet: f32) -> f32 {
+        let mut reverb = Reverb::new(44100, 64, &ReverbBackend::Freeverb);
+        reverb.set_parameters(&ReverbParameters {
+            width: 1_f32,
+            wet,
+            ..ReverbParameters::default()
+        });
+
+        let mut input = vec![0_f32; 4096];
+        input[0] = 1_f32;
+        let mut output_left = vec![0_f32; input.len()];
+        let mut output_right = vec![0_f32; input.len()];
+        reverb.process(&input, &input, &mut output_left, &mut output_right);
+
+        output_left
+            .iter()
+            .chain(&output_right)
+            .map(|value| value * value)
+            .sum()
     }
 
-    const fn set_damp(&mut self, value: f32) {
-        self.damp1 = value;
-        self.damp2 = 1_f32 - value;
+    /// Creates a pseudo-random signal, uniform between -0.5 and 0.5.
+    fn create_noise(length: usize, seed: u32) -> Vec<f32> {
+        let mut seed = seed;
+        (0..length)
+            .map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                (seed >> 8) as f32 / (1 << 24) as f32 - 0.5_f32
+            })
+            .collect()
     }
-}
-
-struct AllPassFilter {
-    buffer: Vec<f32>,
-
-    buffer_index: usize,
-
-    feedback: f32,
-}
 
-impl AllPassFilter {
-    fn new(buffer_size: usize) -> Self {
-        Self {
-            buffer: vec![0_f32; buffer_size],
-            buffer_index: 0,
-            feedback: 0_f32,
+    /// Measures the gain of the reverb in dB for white noise, once the tail has built up.
+    fn get_loudness(backend: &ReverbBackend) -> f64 {
+        const SAMPLE_RATE: usize = 44100;
+
+        let mut reverb = Reverb::new(SAMPLE_RATE as i32, 64, backend);
+        let input = create_noise(4 * SAMPLE_RATE, 1);
+        let mut output_left = vec![0_f32; input.len()];
+        let mut output_right = vec![0_f32; input.len()];
+        for ((input, output_left), output_right) in input
+            .chunks(64)
+            .zip(output_left.chunks_mut(64))
+            .zip(output_right.chunks_mut(64))
+        {
+            reverb.process(input, input, output_left, output_right);
         }
-    }
 
-    fn mute(&mut self) {
-        let buffer_length = self.buffer.len();
-        for i in 0..buffer_length {
-            self.buffer[i] = 0_f32;
-        }
+        let start = 2 * SAMPLE_RATE;
+        let power = |x: &[f32]| x.iter().map(|&x| x as f64 * x as f64).sum::<f64>();
+        let output = 0.5 * (power(&output_left[start..]) + power(&output_right[start..]));
+        10.0 * (output / power(&input[start..])).log10()
     }
 
-    fn process(&mut self, block: &mut [f32]) {
-        let buffer_length = self.buffer.len();
-        let block_length = block.len();
-
-        let mut block_index: usize = 0;
-        while block_index < block_length {
-            if self.buffer_index == buffer_length {
-                self.buffer_index = 0;
-            }
-
-            let src_rem = buffer_length - self.buffer_index;
-            let dst_rem = block_length - block_index;
-            let rem = cmp::min(src_rem, dst_rem);
-
-            for t in 0..rem {
-                let block_pos = block_index + t;
-                let buffer_pos = self.buffer_index + t;
-
-                let input = block[block_pos];
-
-                let mut bufout = self.buffer[buffer_pos];
-                if bufout.abs() < 1.0E-6_f32 {
-                    bufout = 0_f32;
-                }
-
-                block[block_pos] = bufout - input;
-                self.buffer[buffer_pos] = input + (bufout * self.feedback);
-            }
-
-            self.buffer_index += rem;
-            block_index += rem;
+    #[test]
+    fn backends_are_about_as_loud_as_freeverb() {
+        // Decaying noise, which gives the same loudness as any impulse response of unit energy.
+        let length = 22050;
+        let decay = |(i, x): (usize, f32)| x * (-6.9_f32 * i as f32 / length as f32).exp();
+        let left = create_noise(length, 2).into_iter().enumerate().map(decay);
+        let right = create_noise(length, 3).into_iter().enumerate().map(decay);
+        let impulse_response =
+            ImpulseResponse::new(44100, left.collect(), right.collect()).unwrap();
+
+        let freeverb = get_loudness(&ReverbBackend::Freeverb);
+        let backends = [
+            (
+                "convolution",
+                ReverbBackend::Convolution(Arc::new(impulse_response)),
+            ),
+            ("FDN 8", ReverbBackend::Fdn8),
+            ("FDN 16", ReverbBackend::Fdn16),
+        ];
+        for (name, backend) in backends {
+            let loudness = get_loudness(&backend);
+            assert!(
+                (loudness - freeverb).abs() < 1.0,
+                "Freeverb: {freeverb} dB, {name}: {loudness} dB"
+            );
         }
     }
 
-    fn set_feedback(&mut self, value: f32) {
-        self.feedback = value;
+    #[test]
+    fn wet_above_a_third_is_louder() {
+        let quiet = get_energy(1_f32 / 3_f32);
+        let loud = get_energy(2_f32 / 3_f32);
+
+        assert!(quiet > 0_f32);
+        assert!((loud / quiet - 4_f32).abs() < 1.0E-3_f32);
     }
 }
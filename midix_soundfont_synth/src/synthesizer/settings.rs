@@ -1,7 +1,12 @@
 use crate::prelude::*;
 
 /// Specifies a set of parameters for synthesis.
-#[derive(Copy, Clone)]
+///
+/// # Remarks
+///
+/// The settings are not [`Copy`], as the reverb backend can hold an impulse response
+/// which is shared with [`Arc`](std::sync::Arc). Use [`Clone`] to copy them.
+#[derive(Clone)]
 pub struct SynthesizerSettings {
     /// The sample rate for synthesis.
     pub sample_rate: i32,
@@ -11,12 +16,47 @@ pub struct SynthesizerSettings {
     pub maximum_polyphony: usize,
     /// The value indicating whether reverb and chorus are enabled.
     pub enable_reverb_and_chorus: bool,
+    /// The number of MIDI channels, which are grouped into ports of 16 channels.
+    pub channel_count: usize,
+    /// The reverb engine behind the reverb send.
+    pub reverb_backend: ReverbBackend,
+    /// The number of stereo outputs which the channels are routed to by [`Synthesizer::render_multi`].
+    pub output_count: usize,
+    /// How reverb and chorus are applied by [`Synthesizer::render_multi`].
+    pub output_effects: OutputEffects,
+    /// The number of threads which render the voices.
+    /// With more than one, worker threads are started to render batches of voices
+    /// alongside the thread which calls [`Synthesizer::render`].
+    /// The output is the same with any number of threads.
+    pub thread_count: usize,
+}
+
+/// Specifies how reverb and chorus are applied when rendering to multiple outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputEffects {
+    /// A single reverb and chorus process the sends of all the channels,
+    /// and their returns are written to two extra outputs after the channel outputs,
+    /// the reverb first and the chorus second.
+    #[default]
+    Shared,
+    /// Each output has its own reverb and chorus, which process the sends of the channels
+    /// routed to it, and their returns are mixed into that output.
+    PerOutput,
+    /// There is no built-in reverb and chorus. The reverb and chorus sends of all the channels
+    /// are written to two extra outputs after the channel outputs, the reverb first and the chorus
+    /// second, so that they can be processed by external effects.
+    /// This applies regardless of `enable_reverb_and_chorus`, which only decides whether
+    /// [`Synthesizer::render`] still mixes in the built-in reverb and chorus.
+    Sends,
 }
 
 impl SynthesizerSettings {
     const DEFAULT_BLOCK_SIZE: usize = 64;
     const DEFAULT_MAXIMUM_POLYPHONY: usize = 64;
     const DEFAULT_ENABLE_REVERB_AND_CHORUS: bool = true;
+    const DEFAULT_CHANNEL_COUNT: usize = 16;
+    const DEFAULT_OUTPUT_COUNT: usize = 1;
+    const DEFAULT_THREAD_COUNT: usize = 1;
 
     /// Initializes a new instance of synthesizer settings.
     ///
@@ -29,6 +69,11 @@ impl SynthesizerSettings {
             block_size: SynthesizerSettings::DEFAULT_BLOCK_SIZE,
             maximum_polyphony: SynthesizerSettings::DEFAULT_MAXIMUM_POLYPHONY,
             enable_reverb_and_chorus: SynthesizerSettings::DEFAULT_ENABLE_REVERB_AND_CHORUS,
+            channel_count: SynthesizerSettings::DEFAULT_CHANNEL_COUNT,
+            reverb_backend: ReverbBackend::Freeverb,
+            output_count: SynthesizerSettings::DEFAULT_OUTPUT_COUNT,
+            output_effects: OutputEffects::Shared,
+            thread_count: SynthesizerSettings::DEFAULT_THREAD_COUNT,
         }
     }
 
@@ -36,6 +81,9 @@ impl SynthesizerSettings {
         SynthesizerSettings::check_sample_rate(self.sample_rate)?;
         SynthesizerSettings::check_block_size(self.block_size)?;
         SynthesizerSettings::check_maximum_polyphony(self.maximum_polyphony)?;
+        SynthesizerSettings::check_channel_count(self.channel_count)?;
+        SynthesizerSettings::check_output_count(self.output_count, self.channel_count)?;
+        SynthesizerSettings::check_thread_count(self.thread_count)?;
 
         Ok(())
     }
@@ -63,4 +111,28 @@ impl SynthesizerSettings {
 
         Ok(())
     }
+
+    fn check_channel_count(value: usize) -> Result<(), SynthesizerError> {
+        if !(16..=256).contains(&value) || !value.is_multiple_of(16) {
+            return Err(SynthesizerError::ChannelCountOutOfRange(value));
+        }
+
+        Ok(())
+    }
+
+    fn check_output_count(value: usize, channel_count: usize) -> Result<(), SynthesizerError> {
+        if !(1..=channel_count).contains(&value) {
+            return Err(SynthesizerError::OutputCountOutOfRange(value));
+        }
+
+        Ok(())
+    }
+
+    fn check_thread_count(value: usize) -> Result<(), SynthesizerError> {
+        if !(1..=64).contains(&value) {
+            return Err(SynthesizerError::ThreadCountOutOfRange(value));
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,326 @@
+use super::{
+    ChorusParameters, ChorusPreset, RealTimeSection, ReverbParameters, ReverbPreset, Synthesizer,
+    Tuning,
+};
+
+/// The MIDI standard which the synthesizer was last reset to by a system exclusive message.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MidiStandard {
+    /// No system reset has been received, or GM was turned off.
+    #[default]
+    None,
+    /// General MIDI Level 1.
+    Gm,
+    /// General MIDI Level 2.
+    Gm2,
+    /// Roland GS.
+    Gs,
+    /// Yamaha XG.
+    Xg,
+}
+
+const UNIVERSAL_NON_REAL_TIME: u8 = 0x7E;
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+const ROLAND: u8 = 0x41;
+const YAMAHA: u8 = 0x43;
+
+const ROLAND_GS: u8 = 0x42;
+const ROLAND_DATA_SET: u8 = 0x12;
+const YAMAHA_XG: u8 = 0x4C;
+
+impl Synthesizer {
+    /// Processes a system exclusive message.
+    ///
+    /// The following messages are supported:
+    ///
+    /// * GM System On/Off and GM2 System On.
+    /// * Universal Master Volume, Master Fine Tuning and Master Coarse Tuning.
+    /// * GS Reset, and the GS master volume, master tune, master key shift,
+    ///   reverb (macro, character, level, time, pre-delay), chorus (macro, level, feedback, delay,
+    ///   rate, depth) and "use for rhythm part" parameters.
+    /// * XG System On, and the XG master volume, master tune, transpose,
+    ///   reverb type and part mode parameters.
+    /// * MIDI Tuning Standard bulk dumps, single note tuning changes
+    ///   and scale/octave tuning (1-byte and 2-byte forms).
+    ///   Up to 128 tuning programs are kept, and dumps of further programs are ignored,
+    ///   unless more have been loaded with [`Synthesizer::set_tuning_program`].
+    ///
+    /// Other messages are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The message, with or without the leading 0xF0 and the trailing 0xF7.
+    pub fn process_sysex(&mut self, data: &[u8]) {
+        let _section = RealTimeSection::enter();
+
+        let data = data.strip_prefix(&[0xF0]).unwrap_or(data);
+        let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+
+        match data {
+            // The device ID is not checked, as the synthesizer responds to any.
+            [UNIVERSAL_NON_REAL_TIME, _, 0x09, sub_id, ..] => self.process_gm_system(*sub_id),
+            [
+                UNIVERSAL_NON_REAL_TIME | UNIVERSAL_REAL_TIME,
+                _,
+                0x08,
+                body @ ..,
+            ] => self.process_midi_tuning(body),
+            [UNIVERSAL_REAL_TIME, _, 0x04, sub_id, lsb, msb, ..] => {
+                self.process_device_control(*sub_id, *lsb, *msb)
+            }
+            [ROLAND, _, ROLAND_GS, ROLAND_DATA_SET, body @ ..] => self.process_gs_data_set(body),
+            [YAMAHA, device, YAMAHA_XG, body @ ..] if device & 0xF0 == 0x10 => {
+                self.process_xg_parameter_change(body)
+            }
+            _ => (),
+        }
+    }
+
+    /// Gets the MIDI standard which the synthesizer was last reset to.
+    pub fn get_midi_standard(&self) -> MidiStandard {
+        self.midi_standard
+    }
+
+    fn process_gm_system(&mut self, sub_id: u8) {
+        match sub_id {
+            0x01 => self.system_reset(MidiStandard::Gm), // GM System On
+            0x02 => self.midi_standard = MidiStandard::None, // GM System Off
+            0x03 => self.system_reset(MidiStandard::Gm2), // GM2 System On
+            _ => (),
+        }
+    }
+
+    fn process_device_control(&mut self, sub_id: u8, lsb: u8, msb: u8) {
+        let value = (lsb as u16 & 0x7F) | ((msb as u16 & 0x7F) << 7);
+
+        match sub_id {
+            0x01 => self.system_volume = (1_f32 / 16383_f32) * value as f32, // Master Volume
+            0x03 => self.master_fine_tune = (100_f32 / 8192_f32) * (value as f32 - 8192_f32), // Master Fine Tuning
+            0x04 => self.master_coarse_tune = msb as i32 - 64, // Master Coarse Tuning
+            _ => (),
+        }
+    }
+
+    fn process_midi_tuning(&mut self, body: &[u8]) {
+        const NAME_LENGTH: usize = 16;
+
+        match body {
+            [0x01, program, rest @ ..] => {
+                self.load_bulk_tuning(0, *program, &rest[NAME_LENGTH.min(rest.len())..])
+            } // Bulk Tuning Dump
+            [0x04, bank, program, rest @ ..] => {
+                self.load_bulk_tuning(*bank, *program, &rest[NAME_LENGTH.min(rest.len())..])
+            } // Bulk Tuning Dump (Bank)
+            [0x02, program, count, changes @ ..] => {
+                self.change_note_tuning(0, *program, *count, changes)
+            } // Single Note Tuning Change
+            [0x07, bank, program, count, changes @ ..] => {
+                self.change_note_tuning(*bank, *program, *count, changes)
+            } // Single Note Tuning Change (Bank)
+            [0x08, f, g, h, values @ ..] if values.len() >= 12 => {
+                // Scale/Octave Tuning, 1-byte form, in cents with the center at 64
+                let cents = core::array::from_fn(|i| (values[i] & 0x7F) as f32 - 64_f32);
+                self.set_octave_tuning(*f, *g, *h, &cents);
+            }
+            [0x09, f, g, h, values @ ..] if values.len() >= 24 => {
+                // Scale/Octave Tuning, 2-byte form, 14-bit with the center at 0x2000 for ±100 cents
+                let cents = core::array::from_fn(|i| {
+                    let value =
+                        ((values[2 * i] as u16 & 0x7F) << 7) | (values[2 * i + 1] as u16 & 0x7F);
+                    (100_f32 / 8192_f32) * (value as f32 - 8192_f32)
+                });
+                self.set_octave_tuning(*f, *g, *h, &cents);
+            }
+            _ => (),
+        }
+    }
+
+    fn load_bulk_tuning(&mut self, bank: u8, program: u8, data: &[u8]) {
+        // Three bytes for each key, followed by the checksum.
+        if data.len() < 3 * 128 {
+            return;
+        }
+
+        let mut tuning = Tuning::EQUAL_TEMPERAMENT;
+        for (key, frequency) in data.chunks_exact(3).take(128).enumerate() {
+            tuning.set_mts_frequency(key as u8, frequency);
+        }
+        self.load_tuning_program(bank, program, tuning);
+    }
+
+    fn change_note_tuning(&mut self, bank: u8, program: u8, count: u8, changes: &[u8]) {
+        let mut tuning = self
+            .get_tuning_program(bank, program)
+            .copied()
+            .unwrap_or_default();
+        for change in changes.chunks_exact(4).take(count as usize) {
+            tuning.set_mts_frequency(change[0], &change[1..]);
+        }
+        self.load_tuning_program(bank, program, tuning);
+    }
+
+    /// Loads a tuning program, unless the storage is full.
+    /// A new program is only added while there is room for it, so that SysEx does not allocate.
+    fn load_tuning_program(&mut self, bank: u8, program: u8, tuning: Tuning) {
+        let id = Synthesizer::get_tuning_id(bank, program);
+        if self.tunings.len() < self.tunings.capacity() || self.tunings.contains_key(&id) {
+            self.set_tuning_program(bank, program, tuning);
+        }
+    }
+
+    /// The channel mask is split across three bytes, with channel 0 in the lowest bit of the last one.
+    fn set_octave_tuning(&mut self, f: u8, g: u8, h: u8, cents: &[f32; 12]) {
+        let mask = (h as u32 & 0x7F) | ((g as u32 & 0x7F) << 7) | ((f as u32 & 0x03) << 14);
+
+        let mut tuning = Tuning::EQUAL_TEMPERAMENT;
+        tuning.set_octave_tuning(cents);
+        for (channel, channel_info) in self.channels.iter_mut().enumerate().take(16) {
+            if mask & (1 << channel) != 0 {
+                channel_info.set_tuning(&tuning);
+            }
+        }
+    }
+
+    fn process_gs_data_set(&mut self, body: &[u8]) {
+        // The address, at least one byte of data and the checksum.
+        if body.len() < 5 {
+            return;
+        }
+
+        let (message, checksum) = body.split_at(body.len() - 1);
+        let sum = message
+            .iter()
+            .fold(checksum[0] as u32, |sum, &x| sum + x as u32);
+        if sum & 0x7F != 0 {
+            return;
+        }
+
+        let (address, data) = message.split_at(3);
+        match (address, data) {
+            ([0x40, 0x00, 0x7F], _) => self.system_reset(MidiStandard::Gs), // GS Reset
+            ([0x40, 0x00, 0x00], [a, b, c, d, ..]) => {
+                // Master Tune, in 0.1 cent steps with the center at 0x0400
+                let value = Synthesizer::nibbles_to_u16(*a, *b, *c, *d);
+                self.master_fine_tune = 0.1_f32 * (value as f32 - 1024_f32);
+            }
+            ([0x40, 0x00, 0x04], [value, ..]) => {
+                // Master Volume
+                self.system_volume = (1_f32 / 127_f32) * *value as f32;
+            }
+            ([0x40, 0x00, 0x05], [value, ..]) => self.master_coarse_tune = *value as i32 - 64, // Master Key Shift
+            ([0x40, 0x01, 0x30], [value, ..]) => {
+                // Reverb Macro
+                if let Some(preset) = ReverbPreset::from_gs_macro(*value) {
+                    self.set_reverb_preset(preset);
+                }
+            }
+            ([0x40, 0x01, 0x31], [value, ..]) => self.update_reverb_parameters(|parameters| {
+                // Reverb Character
+                parameters.set_gs_character(*value);
+            }),
+            ([0x40, 0x01, 0x33], [value, ..]) => self.update_reverb_parameters(|parameters| {
+                // Reverb Level, where the default of 64 is the default level
+                parameters.wet = (2_f32 / 3_f32 / 127_f32) * *value as f32;
+            }),
+            ([0x40, 0x01, 0x34], [value, ..]) => self.update_reverb_parameters(|parameters| {
+                // Reverb Time
+                parameters.set_gs_time(*value);
+            }),
+            ([0x40, 0x01, 0x37], [value, ..]) => self.update_reverb_parameters(|parameters| {
+                // Reverb Pre-Delay Time, in milliseconds
+                parameters.pre_delay = 0.001_f32 * *value as f32;
+            }),
+            ([0x40, 0x01, 0x38], [value, ..]) => {
+                // Chorus Macro
+                if let Some(preset) = ChorusPreset::from_gs_macro(*value) {
+                    self.set_chorus_preset(preset);
+                }
+            }
+            ([0x40, 0x01, 0x3A], [value, ..]) => self.update_chorus_parameters(|parameters| {
+                // Chorus Level, where the default of 64 is the unity gain
+                parameters.level = (1_f32 / 64_f32) * *value as f32;
+            }),
+            ([0x40, 0x01, 0x3B], [value, ..]) => self.update_chorus_parameters(|parameters| {
+                // Chorus Feedback
+                parameters.feedback =
+                    (ChorusParameters::MAXIMUM_FEEDBACK / 127_f32) * *value as f32;
+            }),
+            ([0x40, 0x01, 0x3C], [value, ..]) => self.update_chorus_parameters(|parameters| {
+                // Chorus Delay
+                parameters.delay = (ChorusParameters::MAXIMUM_DELAY / 127_f32) * *value as f32;
+            }),
+            ([0x40, 0x01, 0x3D], [value, ..]) => self.update_chorus_parameters(|parameters| {
+                // Chorus Rate, up to about 10 Hz
+                parameters.rate = (10_f32 / 127_f32) * *value as f32;
+            }),
+            ([0x40, 0x01, 0x3E], [value, ..]) => self.update_chorus_parameters(|parameters| {
+                // Chorus Depth
+                parameters.depth = (ChorusParameters::MAXIMUM_DEPTH / 127_f32) * *value as f32;
+            }),
+            ([0x40, block, 0x15], [value, ..]) if block & 0xF0 == 0x10 => {
+                // Use For Rhythm Part
+                if let Some(channel) = Synthesizer::gs_block_to_channel(block & 0x0F) {
+                    self.set_channel_percussion(channel, *value != 0);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn process_xg_parameter_change(&mut self, body: &[u8]) {
+        match body {
+            [0x00, 0x00, 0x7E, ..] => self.system_reset(MidiStandard::Xg), // XG System On
+            [0x00, 0x00, 0x00, a, b, c, d, ..] => {
+                // Master Tune, in 0.1 cent steps with the center at 0x0400
+                let value = Synthesizer::nibbles_to_u16(*a, *b, *c, *d);
+                self.master_fine_tune = 0.1_f32 * (value as f32 - 1024_f32);
+            }
+            [0x00, 0x00, 0x04, value, ..] => self.system_volume = (1_f32 / 127_f32) * *value as f32, // Master Volume
+            [0x00, 0x00, 0x06, value, ..] => self.master_coarse_tune = *value as i32 - 64, // Transpose
+            [0x02, 0x01, 0x00, msb, lsb, ..] => match ReverbPreset::from_xg_type(*msb, *lsb) {
+                // Reverb Type
+                Some(preset) => self.set_reverb_preset(preset),
+                None if *msb == 0 => {
+                    self.update_reverb_parameters(|parameters| parameters.wet = 0_f32)
+                }
+                None => (),
+            },
+            [0x08, part, 0x07, value, ..] => self.set_channel_percussion(*part, *value != 0), // Part Mode
+            _ => (),
+        }
+    }
+
+    /// Resets the synthesizer and the system parameters to their defaults.
+    fn system_reset(&mut self, standard: MidiStandard) {
+        for i in 0..self.channels.len() {
+            let percussion = self.is_default_percussion_channel(i);
+            self.channels[i].set_percussion_channel(percussion);
+        }
+
+        self.reset();
+
+        self.midi_standard = standard;
+        self.system_volume = 1_f32;
+        self.master_fine_tune = 0_f32;
+        self.master_coarse_tune = 0;
+        self.set_reverb_parameters(&ReverbParameters::default());
+        self.set_chorus_parameters(&ChorusParameters::default());
+    }
+
+    /// GS parts are numbered from 1, with part 10 (the drum part) at block 0.
+    fn gs_block_to_channel(block: u8) -> Option<u8> {
+        match block {
+            0x0 => Some(9),
+            0x1..=0x9 => Some(block - 1),
+            0xA..=0xF => Some(block),
+            _ => None,
+        }
+    }
+
+    fn nibbles_to_u16(a: u8, b: u8, c: u8, d: u8) -> u16 {
+        ((a as u16 & 0x0F) << 12)
+            | ((b as u16 & 0x0F) << 8)
+            | ((c as u16 & 0x0F) << 4)
+            | (d as u16 & 0x0F)
+    }
+}
@@ -0,0 +1,461 @@
+use bevy_platform::prelude::*;
+
+use crate::prelude::*;
+
+/// A tuning table, which assigns a pitch to each of the 128 MIDI keys.
+///
+/// The pitch of a key is stored as a fractional MIDI key number,
+/// where 69 is A4 at 440 Hz and each step is a 12-TET semitone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    pitches: [f32; 128],
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self::EQUAL_TEMPERAMENT
+    }
+}
+
+impl Tuning {
+    /// The standard 12-tone equal temperament with A4 at 440 Hz.
+    pub const EQUAL_TEMPERAMENT: Tuning = {
+        let mut pitches = [0_f32; 128];
+        let mut key = 0;
+        while key < 128 {
+            pitches[key] = key as f32;
+            key += 1;
+        }
+        Tuning { pitches }
+    };
+
+    const REFERENCE_FREQUENCY: f64 = 440.0;
+    const REFERENCE_KEY: f64 = 69.0;
+
+    /// Creates a tuning from the frequency of each key in Hz.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequencies` - The frequencies of the keys, from key 0 to key 127.
+    pub fn from_frequencies(frequencies: &[f64; 128]) -> Self {
+        let mut tuning = Tuning::EQUAL_TEMPERAMENT;
+        for (key, &frequency) in frequencies.iter().enumerate() {
+            tuning.set_frequency(key as u8, frequency);
+        }
+        tuning
+    }
+
+    /// Creates a tuning from a Scala scale file (.scl) and an optional keyboard mapping file (.kbm).
+    ///
+    /// Without a keyboard mapping, the first degree of the scale is mapped to key 60,
+    /// and the scale is transposed so that key 69 sounds at 440 Hz.
+    /// Keys which the mapping leaves unmapped keep their equal temperament pitch.
+    ///
+    /// # Arguments
+    ///
+    /// * `scl` - The contents of the scale file.
+    /// * `kbm` - The contents of the keyboard mapping file.
+    pub fn from_scala(scl: &str, kbm: Option<&str>) -> Result<Self, TuningError> {
+        let scale = ScalaScale::parse(scl)?;
+        let mapping = match kbm {
+            Some(kbm) => KeyboardMapping::parse(kbm)?,
+            None => KeyboardMapping::default(),
+        };
+
+        let reference_cents = mapping
+            .get_degree(mapping.reference_key, scale.len())
+            .map(|degree| scale.get_cents(degree))
+            .ok_or(TuningError::UnmappedReferenceKey(mapping.reference_key))?;
+        let reference_pitch = Tuning::REFERENCE_KEY
+            + 12.0 * (mapping.reference_frequency / Tuning::REFERENCE_FREQUENCY).log2();
+
+        let mut tuning = Tuning::EQUAL_TEMPERAMENT;
+        for key in mapping.first_key..=mapping.last_key.min(127) {
+            if let Some(degree) = mapping.get_degree(key, scale.len()) {
+                let cents = scale.get_cents(degree) - reference_cents;
+                tuning.pitches[key as usize] = (reference_pitch + 0.01 * cents) as f32;
+            }
+        }
+
+        Ok(tuning)
+    }
+
+    /// Gets the pitch of a key, as a fractional MIDI key number.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to query.
+    pub fn get_pitch(&self, key: u8) -> f32 {
+        self.pitches[key as usize & 0x7F]
+    }
+
+    /// Sets the pitch of a key, as a fractional MIDI key number.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retune.
+    /// * `pitch` - The new pitch of the key.
+    pub fn set_pitch(&mut self, key: u8, pitch: f32) {
+        self.pitches[key as usize & 0x7F] = pitch;
+    }
+
+    /// Gets the frequency of a key in Hz.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to query.
+    pub fn get_frequency(&self, key: u8) -> f64 {
+        let pitch = self.get_pitch(key) as f64;
+        Tuning::REFERENCE_FREQUENCY * 2_f64.powf((pitch - Tuning::REFERENCE_KEY) / 12.0)
+    }
+
+    /// Sets the frequency of a key in Hz.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to retune.
+    /// * `frequency` - The new frequency of the key.
+    pub fn set_frequency(&mut self, key: u8, frequency: f64) {
+        let pitch = Tuning::REFERENCE_KEY + 12.0 * (frequency / Tuning::REFERENCE_FREQUENCY).log2();
+        self.set_pitch(key, pitch as f32);
+    }
+
+    /// Applies a frequency in the MIDI Tuning Standard format:
+    /// a semitone followed by a 14-bit fraction of a semitone.
+    /// The value 0x7F 0x7F 0x7F means no change.
+    pub(crate) fn set_mts_frequency(&mut self, key: u8, data: &[u8]) {
+        if let [semitone, msb, lsb] = *data {
+            if (semitone, msb, lsb) == (0x7F, 0x7F, 0x7F) {
+                return;
+            }
+
+            let fraction = ((msb as u16 & 0x7F) << 7) | (lsb as u16 & 0x7F);
+            let pitch = (semitone & 0x7F) as f32 + (1_f32 / 16384_f32) * fraction as f32;
+            self.set_pitch(key, pitch);
+        }
+    }
+
+    /// Detunes each pitch class of the equal temperament by the given amount in cents,
+    /// as in the MIDI Tuning Standard scale/octave tuning.
+    pub(crate) fn set_octave_tuning(&mut self, cents: &[f32; 12]) {
+        for (key, pitch) in self.pitches.iter_mut().enumerate() {
+            *pitch = key as f32 + 0.01_f32 * cents[key % 12];
+        }
+    }
+}
+
+/// The scale of a Scala file, in cents from the first degree.
+struct ScalaScale {
+    // The pitches of the degrees 1 to n, where the last one is the period of the scale.
+    cents: Vec<f64>,
+}
+
+impl ScalaScale {
+    fn parse(scl: &str) -> Result<Self, TuningError> {
+        // The first line is the description, which can be empty.
+        let mut lines = scl.lines().filter(|line| !line.starts_with('!'));
+        lines.next().ok_or(TuningError::InvalidScale)?;
+
+        let count: usize = lines
+            .next()
+            .and_then(ScalaScale::first_token)
+            .and_then(|token| token.parse().ok())
+            .ok_or(TuningError::InvalidScale)?;
+
+        let cents = lines
+            .take(count)
+            .map(|line| {
+                ScalaScale::first_token(line)
+                    .and_then(ScalaScale::parse_pitch)
+                    .ok_or_else(|| TuningError::InvalidPitch(line.trim().to_string()))
+            })
+            .collect::<Result<Vec<f64>, TuningError>>()?;
+
+        if cents.is_empty() || cents.len() != count {
+            return Err(TuningError::InvalidScale);
+        }
+
+        Ok(Self { cents })
+    }
+
+    fn first_token(line: &str) -> Option<&str> {
+        line.split_whitespace().next()
+    }
+
+    /// A pitch is in cents if it contains a period, and a ratio otherwise.
+    fn parse_pitch(token: &str) -> Option<f64> {
+        if token.contains('.') {
+            return token.parse().ok();
+        }
+
+        let (numerator, denominator) = token.split_once('/').unwrap_or((token, "1"));
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        if numerator <= 0.0 || denominator <= 0.0 {
+            return None;
+        }
+
+        Some(1200.0 * (numerator / denominator).log2())
+    }
+
+    fn len(&self) -> usize {
+        self.cents.len()
+    }
+
+    fn get_cents(&self, degree: i32) -> f64 {
+        let count = self.len() as i32;
+        let period = degree.div_euclid(count);
+        let step = degree.rem_euclid(count);
+
+        let period_cents = self.cents[self.len() - 1];
+        let step_cents = if step == 0 {
+            0.0
+        } else {
+            self.cents[step as usize - 1]
+        };
+
+        period as f64 * period_cents + step_cents
+    }
+}
+
+/// The keyboard mapping of a Scala .kbm file.
+struct KeyboardMapping {
+    first_key: u8,
+    last_key: u8,
+    middle_key: u8,
+    reference_key: u8,
+    reference_frequency: f64,
+    // Zero means the period of the scale.
+    octave_degree: usize,
+    // Empty for a linear mapping.
+    map: Vec<Option<i32>>,
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        Self {
+            first_key: 0,
+            last_key: 127,
+            middle_key: 60,
+            reference_key: 69,
+            reference_frequency: Tuning::REFERENCE_FREQUENCY,
+            octave_degree: 0,
+            map: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardMapping {
+    fn parse(kbm: &str) -> Result<Self, TuningError> {
+        let mut tokens = kbm
+            .lines()
+            .filter(|line| !line.starts_with('!'))
+            .filter_map(|line| line.split_whitespace().next());
+        let mut next = || tokens.next().ok_or(TuningError::InvalidKeyboardMapping);
+
+        let size: usize = KeyboardMapping::parse_number(next()?)?;
+        let first_key = KeyboardMapping::parse_key(next()?)?;
+        let last_key = KeyboardMapping::parse_key(next()?)?;
+        let middle_key = KeyboardMapping::parse_key(next()?)?;
+        let reference_key = KeyboardMapping::parse_key(next()?)?;
+        let reference_frequency: f64 = KeyboardMapping::parse_number(next()?)?;
+        let octave_degree: usize = KeyboardMapping::parse_number(next()?)?;
+
+        let mut map = Vec::with_capacity(size);
+        for _ in 0..size {
+            // Missing entries at the end of the file are unmapped.
+            map.push(match next() {
+                Ok("x") | Err(_) => None,
+                Ok(token) => Some(KeyboardMapping::parse_number(token)?),
+            });
+        }
+
+        if reference_frequency <= 0.0 {
+            return Err(TuningError::InvalidKeyboardMapping);
+        }
+
+        Ok(Self {
+            first_key,
+            last_key,
+            middle_key,
+            reference_key,
+            reference_frequency,
+            octave_degree,
+            map,
+        })
+    }
+
+    fn parse_number<T: core::str::FromStr>(token: &str) -> Result<T, TuningError> {
+        token
+            .parse()
+            .map_err(|_| TuningError::InvalidKeyboardMapping)
+    }
+
+    fn parse_key(token: &str) -> Result<u8, TuningError> {
+        let key: u8 = KeyboardMapping::parse_number(token)?;
+        if key > 127 {
+            return Err(TuningError::InvalidKeyboardMapping);
+        }
+        Ok(key)
+    }
+
+    /// Gets the scale degree which a key is mapped to, if any.
+    fn get_degree(&self, key: u8, scale_length: usize) -> Option<i32> {
+        let offset = key as i32 - self.middle_key as i32;
+        if self.map.is_empty() {
+            return Some(offset);
+        }
+
+        let size = self.map.len() as i32;
+        let octave_degree = if self.octave_degree == 0 {
+            scale_length
+        } else {
+            self.octave_degree
+        } as i32;
+
+        self.map[offset.rem_euclid(size) as usize]
+            .map(|degree| degree + offset.div_euclid(size) * octave_degree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: &str = "! test.scl
+!
+A scale of three degrees
+ 3
+!
+ 100.0 cents
+ 3/2 a fifth
+ 2
+";
+
+    fn assert_pitch(tuning: &Tuning, key: u8, pitch: f32) {
+        let actual = tuning.get_pitch(key);
+        assert!(
+            (actual - pitch).abs() < 1.0E-4_f32,
+            "key {key}: expected {pitch}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn scale_reads_cents_and_ratios() {
+        let scale = ScalaScale::parse(SCALE).unwrap();
+
+        assert_eq!(scale.len(), 3);
+        assert!((scale.get_cents(1) - 100.0).abs() < 1.0E-9);
+        assert!((scale.get_cents(2) - 701.955).abs() < 1.0E-3);
+        assert!((scale.get_cents(3) - 1200.0).abs() < 1.0E-9);
+        assert!((scale.get_cents(-1) - 701.955 + 1200.0).abs() < 1.0E-3);
+    }
+
+    #[test]
+    fn scale_allows_an_empty_description() {
+        let scale = ScalaScale::parse("\n 1\n 2/1\n").unwrap();
+
+        assert_eq!(scale.len(), 1);
+        assert!((scale.get_cents(1) - 1200.0).abs() < 1.0E-9);
+    }
+
+    #[test]
+    fn scale_rejects_invalid_pitches() {
+        assert!(matches!(
+            ScalaScale::parse("Scale\n 2\n 100.0\n -3/2\n"),
+            Err(TuningError::InvalidPitch(pitch)) if pitch == "-3/2"
+        ));
+        assert!(matches!(
+            ScalaScale::parse("Scale\n 3\n 100.0\n 2/1\n"),
+            Err(TuningError::InvalidScale)
+        ));
+    }
+
+    #[test]
+    fn scale_without_mapping_keeps_a4_at_440_hz() {
+        let tuning = Tuning::from_scala(SCALE, None).unwrap();
+
+        assert_pitch(&tuning, 69, 69_f32);
+        // Key 60 is the first degree, three periods below key 69.
+        assert_pitch(&tuning, 60, 33_f32);
+        assert_pitch(&tuning, 61, 34_f32);
+        assert_pitch(&tuning, 62, 40.01955_f32);
+        assert_pitch(&tuning, 63, 45_f32);
+    }
+
+    #[test]
+    fn mapping_leaves_unmapped_keys_in_equal_temperament() {
+        let kbm = "! test.kbm
+! Size of the map
+4
+! First and last keys
+0
+127
+! Middle key, reference key and frequency
+60
+60
+261.6255653
+! Octave degree
+3
+! Mapping
+0
+x
+1
+2
+";
+        let tuning = Tuning::from_scala(SCALE, Some(kbm)).unwrap();
+
+        assert_pitch(&tuning, 60, 60_f32);
+        assert_pitch(&tuning, 61, 61_f32);
+        assert_pitch(&tuning, 62, 61_f32);
+        assert_pitch(&tuning, 63, 67.01955_f32);
+        assert_pitch(&tuning, 64, 72_f32);
+        assert_pitch(&tuning, 65, 65_f32);
+        assert_pitch(&tuning, 59, 55.01955_f32);
+    }
+
+    #[test]
+    fn mapping_fills_missing_entries_with_unmapped_keys() {
+        let kbm = "2\n0\n127\n60\n60\n261.6255653\n0\n0\n";
+        let tuning = Tuning::from_scala(SCALE, Some(kbm)).unwrap();
+
+        assert_pitch(&tuning, 60, 60_f32);
+        assert_pitch(&tuning, 61, 61_f32);
+        // The octave degree of zero is the period of the scale.
+        assert_pitch(&tuning, 62, 72_f32);
+    }
+
+    #[test]
+    fn mapping_rejects_an_unmapped_reference_key() {
+        let kbm = "2\n0\n127\n60\n61\n440.0\n0\n0\nx\n";
+
+        assert!(matches!(
+            Tuning::from_scala(SCALE, Some(kbm)),
+            Err(TuningError::UnmappedReferenceKey(61))
+        ));
+    }
+
+    #[test]
+    fn mapping_rejects_malformed_files() {
+        assert!(matches!(
+            Tuning::from_scala(SCALE, Some("0\n0\n128\n60\n69\n440.0\n0\n")),
+            Err(TuningError::InvalidKeyboardMapping)
+        ));
+        assert!(matches!(
+            Tuning::from_scala(SCALE, Some("0\n0\n127\n60\n")),
+            Err(TuningError::InvalidKeyboardMapping)
+        ));
+    }
+
+    #[test]
+    fn mts_frequency_is_a_semitone_and_a_fraction() {
+        let mut tuning = Tuning::EQUAL_TEMPERAMENT;
+
+        tuning.set_mts_frequency(60, &[0x3C, 0x40, 0x00]);
+        assert_pitch(&tuning, 60, 60.5_f32);
+
+        // No change.
+        tuning.set_mts_frequency(60, &[0x7F, 0x7F, 0x7F]);
+        assert_pitch(&tuning, 60, 60.5_f32);
+    }
+}
@@ -0,0 +1,137 @@
+use super::Synthesizer;
+
+impl Synthesizer {
+    /// Processes a Universal MIDI Packet.
+    ///
+    /// MIDI 1.0 channel voice messages (message type 0x2) are handled like
+    /// [`Synthesizer::process_midi_message`]. MIDI 2.0 channel voice messages
+    /// (message type 0x4) are applied with their full resolution, including
+    /// the per-note controllers. Other message types are ignored.
+    ///
+    /// The group of a packet selects the port, as in [`Synthesizer::process_midi_message_on_port`].
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - The words of the packet. Any words past the packet's size are ignored.
+    pub fn process_universal_midi_packet(&mut self, packet: &[u32]) {
+        let Some(&word0) = packet.first() else {
+            return;
+        };
+
+        match word0 >> 28 {
+            0x2 => self.process_channel_voice_message(
+                Synthesizer::get_group(word0),
+                (word0 >> 16) as u8,
+                ((word0 >> 8) & 0x7F) as u8,
+                (word0 & 0x7F) as u8,
+            ),
+            0x4 => {
+                if let Some(&word1) = packet.get(1) {
+                    self.process_midi2_channel_voice_message(word0, word1);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn process_midi2_channel_voice_message(&mut self, word0: u32, word1: u32) {
+        let status = ((word0 >> 20) & 0x0F) as u8;
+        let index = ((word0 >> 8) & 0x7F) as u8;
+        let low = (word0 & 0xFF) as u8;
+
+        let Some(channel) =
+            self.get_channel_index(Synthesizer::get_group(word0), ((word0 >> 16) & 0x0F) as u8)
+        else {
+            return;
+        };
+
+        // The per-note state is kept by the key of the note-on, before the drum key map,
+        // so that it follows the note if the map changes.
+        match status {
+            0x8 => self.note_off(channel, index), // Note Off
+            0x9 => {
+                // Note On
+                // Attribute type 3 is the pitch of the note in 7.9 fixed point.
+                let pitch = (low == 3).then(|| (1_f32 / 512_f32) * (word1 & 0xFFFF) as f32);
+                self.channels[channel as usize].set_per_note_pitch(index, pitch);
+
+                // Unlike MIDI 1.0, a velocity of zero does not mean note off.
+                let velocity = ((word1 >> 16) as u16).max(1);
+                self.play_note(channel, index, velocity);
+            }
+            0xA => self.channels[channel as usize].set_key_pressure(index, word1), // Polyphonic Key Pressure
+            0xB => self.midi2_control_change(channel, index, word1),               // Controller
+            0xC => {
+                // Program Change
+                let channel_info = &mut self.channels[channel as usize];
+                if word0 & 0x01 != 0 {
+                    channel_info.set_bank(((word1 >> 8) & 0x7F) as u8);
+                }
+                channel_info.set_patch(((word1 >> 24) & 0x7F) as u8);
+            }
+            0xD => self.channels[channel as usize].set_channel_pressure(word1), // Channel Pressure
+            0xE => self.channels[channel as usize].set_pitch_bend_32(word1),    // Pitch Bend
+            0x6 => self.channels[channel as usize].set_per_note_pitch_bend(index, word1), // Per-Note Pitch Bend
+            0x0 => self.channels[channel as usize]
+                .set_registered_per_note_controller(index, low, word1), // Registered Per-Note Controller
+            0x2 => {
+                // Registered Controller
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_rpn_coarse(index);
+                channel_info.set_rpn_fine(low & 0x7F);
+                self.midi2_data_entry(channel, word1);
+            }
+            0x3 => {
+                // Assignable Controller
+                let channel_info = &mut self.channels[channel as usize];
+                channel_info.set_nrpn_coarse(index);
+                channel_info.set_nrpn_fine(low & 0x7F);
+                self.midi2_data_entry(channel, word1);
+            }
+            0xF => self.per_note_management(channel, index, low), // Per-Note Management
+            _ => (),
+        }
+    }
+
+    fn midi2_control_change(&mut self, channel: u8, index: u8, value: u32) {
+        let channel_info = &mut self.channels[channel as usize];
+
+        match index {
+            0x01 => channel_info.set_modulation(value), // Modulation
+            0x07 => channel_info.set_volume(value),     // Channel Volume
+            0x0A => channel_info.set_pan(value),        // Pan
+            0x0B => channel_info.set_expression(value), // Expression
+            0x4A => channel_info.set_brightness(value), // Brightness
+            // The other controllers only have MIDI 1.0 resolution.
+            _ => self.process_channel_command(channel, 0xB0, index, (value >> 25) as u8),
+        }
+    }
+
+    /// Each group of the packets is a port of 16 channels.
+    fn get_group(word0: u32) -> u8 {
+        ((word0 >> 24) & 0x0F) as u8
+    }
+
+    fn midi2_data_entry(&mut self, channel: u8, value: u32) {
+        self.data_entry_coarse(channel, (value >> 25) as u8);
+        self.data_entry_fine(channel, ((value >> 18) & 0x7F) as u8);
+    }
+
+    fn per_note_management(&mut self, channel: u8, key: u8, flags: u8) {
+        let detach = flags & 0x02 != 0;
+        let reset = flags & 0x01 != 0;
+
+        if detach {
+            let controllers = *self.channels[channel as usize].get_per_note_controllers(key);
+            for voice in self.voices.iter_mut() {
+                if voice.channel == channel && voice.note_key == key {
+                    voice.detach_per_note(&controllers);
+                }
+            }
+        }
+
+        if reset {
+            self.channels[channel as usize].reset_per_note_controllers(key);
+        }
+    }
+}
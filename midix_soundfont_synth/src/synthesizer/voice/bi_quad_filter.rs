@@ -1,6 +1,6 @@
 use core::f32::consts;
 
-use crate::prelude::*;
+use crate::{prelude::*, synthesizer::ArrayMath};
 
 pub(crate) struct BiQuadFilter {
     sample_rate: i32,
@@ -75,17 +75,51 @@ impl BiQuadFilter {
         let block_length = block.len();
 
         if self.active {
-            for input in block.iter_mut().take(block_length) {
-                let output = self.a0 * *input + self.a1 * self.x1 + self.a2 * self.x2
-                    - self.a3 * self.y1
-                    - self.a4 * self.y2;
-
-                self.x2 = self.x1;
-                self.x1 = *input;
-                self.y2 = self.y1;
-                self.y1 = output;
-
-                *input = output;
+            let mut x0 = [0_f32; ArrayMath::LANES];
+            let mut x1 = [0_f32; ArrayMath::LANES];
+            let mut x2 = [0_f32; ArrayMath::LANES];
+            let b1 = self.a3 * self.a3 - self.a4;
+            let b2 = self.a3 * self.a4;
+
+            // The feedforward part does not depend on the previous outputs, so it is computed
+            // for a chunk at once. Only the feedback part is left for the per-sample loop.
+            for chunk in block.chunks_mut(ArrayMath::LANES) {
+                for (i, &input) in chunk.iter().enumerate() {
+                    x0[i] = input;
+                    x1[i] = self.x1;
+                    x2[i] = self.x2;
+
+                    self.x2 = self.x1;
+                    self.x1 = input;
+                }
+
+                let feedforward =
+                    ArrayMath::multiply_add3(self.a0, &x0, self.a1, &x1, self.a2, &x2);
+
+                // Two outputs are computed from the same previous outputs,
+                // so that they do not wait for each other.
+                let mut feedforwards = feedforward[..chunk.len()].chunks_exact(2);
+                let mut outputs = chunk.chunks_exact_mut(2);
+                for (output, feedforward) in (&mut outputs).zip(&mut feedforwards) {
+                    let y0 = feedforward[0] - self.a3 * self.y1 - self.a4 * self.y2;
+                    let y1 =
+                        (feedforward[1] - self.a3 * feedforward[0]) + b1 * self.y1 + b2 * self.y2;
+
+                    output[0] = y0;
+                    output[1] = y1;
+                    self.y2 = y0;
+                    self.y1 = y1;
+                }
+                for (output, feedforward) in outputs
+                    .into_remainder()
+                    .iter_mut()
+                    .zip(feedforwards.remainder())
+                {
+                    *output = feedforward - self.a3 * self.y1 - self.a4 * self.y2;
+
+                    self.y2 = self.y1;
+                    self.y1 = *output;
+                }
             }
         } else {
             self.x2 = block[block_length - 2];
@@ -103,3 +137,59 @@ impl BiQuadFilter {
         self.a4 = a2 / a0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Filters with the direct form recurrence, one sample at a time.
+    fn process_reference(filter: &BiQuadFilter, input: &[f32]) -> Vec<f32> {
+        let (mut x1, mut x2, mut y1, mut y2) = (0_f64, 0_f64, 0_f64, 0_f64);
+        input
+            .iter()
+            .map(|&x0| {
+                let x0 = x0 as f64;
+                let y0 = filter.a0 as f64 * x0 + filter.a1 as f64 * x1 + filter.a2 as f64 * x2
+                    - filter.a3 as f64 * y1
+                    - filter.a4 as f64 * y2;
+                (x2, x1, y2, y1) = (x1, x0, y1, y0);
+                y0 as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn process_matches_direct_form() {
+        let settings = SynthesizerSettings::new(44100);
+
+        for (cutoff, resonance) in [(200_f32, 1_f32), (2000_f32, 4_f32), (15000_f32, 10_f32)] {
+            let mut filter = BiQuadFilter::new(&settings);
+            filter.set_low_pass_filter(cutoff, resonance);
+
+            let input: Vec<f32> = (0..1000)
+                .map(|i| if i % 50 < 25 { 0.5_f32 } else { -0.5_f32 })
+                .collect();
+            let expected = process_reference(&filter, &input);
+
+            // The blocks have lengths which are not a multiple of LANES, and odd ones,
+            // so that the pairs of outputs and the chunks are split in every way.
+            let mut actual = input.clone();
+            let mut start = 0;
+            for length in [64, 13, 7, 1, 8, 2, 67].iter().cycle() {
+                if start == actual.len() {
+                    break;
+                }
+                let end = (start + length).min(actual.len());
+                filter.process(&mut actual[start..end]);
+                start = end;
+            }
+
+            for (i, (actual, expected)) in actual.iter().zip(&expected).enumerate() {
+                assert!(
+                    (actual - expected).abs() < 1.0E-4_f32,
+                    "cutoff {cutoff}, index {i}: expected {expected}, got {actual}"
+                );
+            }
+        }
+    }
+}
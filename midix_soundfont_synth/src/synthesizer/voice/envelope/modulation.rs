@@ -1,5 +1,5 @@
 use crate::{
-    prelude::{voice::RegionPair, *},
+    prelude::{voice::ResolvedRegion, *},
     utils,
 };
 
@@ -27,7 +27,12 @@ pub struct ModulationEnvelope {
 }
 
 impl ModulationEnvelope {
-    pub fn new(settings: &SynthesizerSettings, region: &RegionPair, key: u8, velocity: u8) -> Self {
+    pub fn new(
+        settings: &SynthesizerSettings,
+        region: &ResolvedRegion,
+        key: u8,
+        velocity: u8,
+    ) -> Self {
         // According to the implementation of TinySoundFont, the attack time should be adjusted by the velocity.
         let delay = region.get_delay_modulation_envelope();
         let attack = region.get_attack_modulation_envelope() * ((145 - velocity) as f32 / 144_f32);
@@ -1,5 +1,5 @@
 use crate::{
-    prelude::{voice::RegionPair, *},
+    prelude::{voice::ResolvedRegion, *},
     utils,
 };
 
@@ -25,7 +25,7 @@ pub struct VolumeEnvelope {
 }
 
 impl VolumeEnvelope {
-    pub fn new(settings: &SynthesizerSettings, region: &RegionPair, key: u8) -> Self {
+    pub fn new(settings: &SynthesizerSettings, region: &ResolvedRegion, key: u8) -> Self {
         // If the release time is shorter than 10 ms, it will be clamped to 10 ms to avoid pop noise.
         let delay = region.get_delay_volume_envelope();
         let attack = region.get_attack_volume_envelope();
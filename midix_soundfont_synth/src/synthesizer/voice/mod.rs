@@ -9,12 +9,18 @@ pub(super) use region::*;
 mod oscillator;
 use oscillator::*;
 
+mod portamento;
+use portamento::*;
+
 mod bi_quad_filter;
 use bi_quad_filter::*;
 
+mod pool;
+pub(super) use pool::*;
+
 use crate::{prelude::*, utils};
 
-use super::SynthChannel;
+use super::{PerNoteControllers, SynthChannel};
 
 pub(crate) struct Voice {
     block_size: usize,
@@ -27,6 +33,7 @@ pub(crate) struct Voice {
 
     oscillator: Oscillator,
     filter: BiQuadFilter,
+    portamento: Portamento,
 
     pub(crate) block: Vec<f32>,
 
@@ -44,8 +51,16 @@ pub(crate) struct Voice {
     pub(crate) current_reverb_send: f32,
     pub(crate) current_chorus_send: f32,
 
+    // Set when the sostenuto pedal was pressed while the note was held down.
+    sustained_by_sostenuto: bool,
+
+    // A snapshot of the per-note controllers, taken when the note is detached from its key.
+    detached_per_note: Option<PerNoteControllers>,
+
     pub(crate) exclusive_class: i32,
     pub(crate) channel: u8,
+    // The key of the note, before the drum key map, which the note-off matches.
+    pub(crate) note_key: u8,
     pub(crate) key: u8,
 
     note_gain: f32,
@@ -78,23 +93,31 @@ pub(crate) struct Voice {
 }
 
 impl Voice {
+    const SOFT_PEDAL_CUTOFF_FACTOR: f32 = 0.6;
+
+    /// Creates a voice which renders into the given block,
+    /// so that the block can be reused from a voice which has finished.
     pub(crate) fn new(
         settings: &SynthesizerSettings,
-        region: &RegionPair,
+        region: &ResolvedRegion,
         channel: u8,
+        note_key: u8,
         key: u8,
-        velocity: u8,
+        velocity: u16,
+        block: Vec<f32>,
     ) -> Self {
         // this is used elsewhere...really thinking we should
         // just use the region
         let exclusive_class = region.get_exclusive_class();
 
+        let velocity_7 = (velocity >> 9) as u8;
+
         let note_gain = if velocity > 0 {
             // According to the Polyphone's implementation, the initial attenuation should be reduced to 40%.
             // I'm not sure why, but this indeed improves the loudness variability.
             let sample_attenuation = 0.4_f32 * region.get_initial_attenuation();
             let filter_attenuation = 0.5_f32 * region.get_initial_filter_q();
-            let decibels = 2_f32 * utils::linear_to_decibels(velocity as f32 / 127_f32)
+            let decibels = 2_f32 * utils::linear_to_decibels(velocity as f32 / 65535_f32)
                 - sample_attenuation
                 - filter_attenuation;
             utils::decibels_to_linear(decibels)
@@ -123,7 +146,7 @@ impl Voice {
         let instrument_chorus = 0.01_f32 * region.get_chorus_effects_send();
 
         let vol_env = VolumeEnvelope::new(settings, region, key);
-        let mod_env = ModulationEnvelope::new(settings, region, key, velocity);
+        let mod_env = ModulationEnvelope::new(settings, region, key, velocity_7);
 
         let vib_lfo = Lfo::new(
             settings,
@@ -142,6 +165,8 @@ impl Voice {
         filter.clear_buffer();
         filter.set_low_pass_filter(cutoff, resonance);
 
+        let portamento = Portamento::new(settings);
+
         let smoothed_cutoff = cutoff;
 
         let voice_state = VoiceState::Playing;
@@ -158,7 +183,8 @@ impl Voice {
             mod_lfo,
             oscillator,
             filter,
-            block: vec![0_f32; settings.block_size],
+            portamento,
+            block,
             previous_mix_gain_left: 0_f32,
             previous_mix_gain_right: 0_f32,
             current_mix_gain_left: 0_f32,
@@ -167,8 +193,11 @@ impl Voice {
             previous_chorus_send: 0_f32,
             current_reverb_send: 0_f32,
             current_chorus_send: 0_f32,
+            sustained_by_sostenuto: false,
+            detached_per_note: None,
             exclusive_class,
             channel,
+            note_key,
             key,
             note_gain,
             cutoff,
@@ -197,6 +226,52 @@ impl Voice {
         }
     }
 
+    pub(crate) fn is_playing(&self) -> bool {
+        self.voice_state == VoiceState::Playing
+    }
+
+    /// Called when the sostenuto pedal is pressed.
+    /// Only the notes which are held down at that moment will be sustained.
+    pub(crate) fn catch_sostenuto(&mut self) {
+        self.sustained_by_sostenuto = self.is_playing();
+    }
+
+    /// Darkens the tone of a note played with the soft pedal.
+    pub(crate) fn soften(&mut self) {
+        self.cutoff *= Voice::SOFT_PEDAL_CUTOFF_FACTOR;
+        self.smoothed_cutoff = self.cutoff;
+        self.filter.set_low_pass_filter(self.cutoff, self.resonance);
+    }
+
+    /// Moves the voice to another key without retriggering it.
+    ///
+    /// If `glide_time` is given, the pitch glides from where it currently is
+    /// to the new key. Otherwise, the pitch jumps to the new key.
+    pub(crate) fn legato(&mut self, note_key: u8, key: u8, glide_time: Option<f32>) {
+        let current_pitch = self.key as f32 + self.portamento.get_offset();
+        self.note_key = note_key;
+        self.key = key;
+
+        match glide_time {
+            Some(time) => self.portamento.start(current_pitch - key as f32, time),
+            None => self.portamento.stop(),
+        }
+    }
+
+    /// Makes the voice glide to its key, starting from the pitch of `from_key`.
+    pub(crate) fn glide_from(&mut self, from_key: u8, time: f32) {
+        self.portamento
+            .start(from_key as f32 - self.key as f32, time);
+    }
+
+    /// Freezes the per-note controllers of the voice, so that a new note on the same key
+    /// does not affect it anymore.
+    pub(crate) fn detach_per_note(&mut self, controllers: &PerNoteControllers) {
+        if self.detached_per_note.is_none() {
+            self.detached_per_note = Some(*controllers);
+        }
+    }
+
     // /// Note stops immediately without a release sound.
     // ///
     // /// End is *supposed* to begin playing a release sound. this is the
@@ -217,7 +292,13 @@ impl Voice {
     /// 3. mod env is just hanging around, so it's definitely not supposed to
     ///    return a bool
     ///
-    pub(crate) fn process(&mut self, data: &[i16], channels: &[SynthChannel]) -> bool {
+    pub(crate) fn process(
+        &mut self,
+        data: &[i16],
+        channels: &[SynthChannel],
+        master_tune: f32,
+        master_transpose: f32,
+    ) -> bool {
         if self.note_gain < utils::NON_AUDIBLE {
             return false;
         }
@@ -235,19 +316,48 @@ impl Voice {
         };
         let vib_lfo = self.vib_lfo.process();
         let mod_lfo = self.mod_lfo.process();
+        let portamento = self.portamento.process();
+
+        let pressure = channel_info.get_pressure_modulation(self.note_key);
+        let per_note = self
+            .detached_per_note
+            .unwrap_or(*channel_info.get_per_note_controllers(self.note_key));
 
-        let vib_pitch_change =
-            (0.01_f32 * channel_info.get_modulation() + self.vib_lfo_to_pitch) * vib_lfo;
+        let vib_depth = channel_info.get_modulation()
+            + channel_info.get_modulation_depth_range() * per_note.modulation
+            + pressure.vibrato_depth;
+        let vib_pitch_change = (0.01_f32 * vib_depth + self.vib_lfo_to_pitch) * vib_lfo;
         let mod_pitch_change = self.mod_lfo_to_pitch * mod_lfo + self.mod_env_to_pitch * mod_env;
-        let channel_pitch_change = channel_info.get_tune() + channel_info.get_pitch_bend();
-        let pitch = self.key as f32 + vib_pitch_change + mod_pitch_change + channel_pitch_change;
+        let mut channel_pitch_change =
+            master_tune + channel_info.get_tune() + channel_info.get_pitch_bend() + pressure.pitch;
+        if !channel_info.is_percussion_channel {
+            channel_pitch_change += master_transpose;
+        }
+        // On an MPE member channel, the pitch bend of the master channel applies on top.
+        if let Some(master) = channel_info.get_mpe_master() {
+            channel_pitch_change += channels[master as usize].get_pitch_bend();
+        }
+        channel_pitch_change += SynthChannel::PER_NOTE_PITCH_BEND_RANGE * per_note.pitch_bend;
+        let pitch = per_note
+            .pitch
+            .unwrap_or(channel_info.get_key_pitch(self.key))
+            + portamento
+            + vib_pitch_change
+            + mod_pitch_change
+            + channel_pitch_change;
         if !self.oscillator.process(data, &mut self.block[..], pitch) {
             return false;
         }
 
-        if self.dynamic_cutoff {
-            let cents =
-                self.mod_lfo_to_cutoff as f32 * mod_lfo + self.mod_env_to_cutoff as f32 * mod_env;
+        let cutoff_change = pressure.filter_cutoff
+            + channel_info.get_brightness()
+            + SynthChannel::BRIGHTNESS_RANGE * per_note.brightness;
+
+        // Once the controllers are back to neutral, the cutoff still has to return to where it was.
+        if self.dynamic_cutoff || cutoff_change != 0_f32 || self.smoothed_cutoff != self.cutoff {
+            let cents = self.mod_lfo_to_cutoff as f32 * mod_lfo
+                + self.mod_env_to_cutoff as f32 * mod_env
+                + cutoff_change;
             let factor = utils::cents_to_multiplying_factor(cents);
             let new_cutoff = factor * self.cutoff;
 
@@ -271,14 +381,20 @@ impl Voice {
         let ve = channel_info.get_volume() * channel_info.get_expression();
         let channel_gain = ve * ve;
 
-        let mut mix_gain = self.note_gain * channel_gain * vol_env;
+        let note_ve = per_note.volume * per_note.expression;
+        let mut mix_gain = self.note_gain
+            * channel_gain
+            * note_ve
+            * note_ve
+            * vol_env
+            * (1_f32 + pressure.amplitude);
         if self.dynamic_volume {
             let decibels = self.mod_lfo_to_volume * mod_lfo;
             mix_gain *= utils::decibels_to_linear(decibels);
         }
 
-        let angle =
-            (consts::PI / 200_f32) * (channel_info.get_pan() + self.instrument_pan + 50_f32);
+        let angle = (consts::PI / 200_f32)
+            * (channel_info.get_pan() + 50_f32 * per_note.pan + self.instrument_pan + 50_f32);
         if angle <= 0_f32 {
             self.current_mix_gain_left = mix_gain;
             self.current_mix_gain_right = 0_f32;
@@ -313,7 +429,10 @@ impl Voice {
             return;
         }
 
-        if self.voice_state == VoiceState::ReleaseRequested && !channel_info.get_hold_pedal() {
+        let sustained = channel_info.get_hold_pedal()
+            || (self.sustained_by_sostenuto && channel_info.get_sostenuto());
+
+        if self.voice_state == VoiceState::ReleaseRequested && !sustained {
             self.vol_env.release();
             self.mod_env.release();
             self.oscillator.release();
@@ -1,4 +1,5 @@
-use super::RegionPair;
+use super::ResolvedRegion;
+use crate::synthesizer::ArrayMath;
 
 use crate::prelude::*;
 
@@ -6,6 +7,7 @@ use crate::prelude::*;
 // A fixed-point number is expressed by Int64, whose lower 24 bits represent the fraction part,
 // and the rest represent the integer part.
 // For clarity, fixed-point number variables have a suffix "_fp".
+// Where a chunk of samples does not reach the end, it is interpolated at once by ArrayMath.
 
 #[non_exhaustive]
 pub(crate) struct Oscillator {
@@ -25,12 +27,13 @@ pub(crate) struct Oscillator {
 }
 
 impl Oscillator {
-    const FRAC_BITS: i32 = 24;
+    const FRAC_BITS: i32 = ArrayMath::FRAC_BITS;
     const FRAC_UNIT: i64 = 1_i64 << Oscillator::FRAC_BITS;
     const FP_TO_SAMPLE: f32 = 1_f32 / (32768 * Oscillator::FRAC_UNIT) as f32;
+    const SAMPLE_SCALE: f32 = 1_f32 / 32768_f32;
 
-    pub(crate) fn new(settings: &SynthesizerSettings, region: &RegionPair) -> Self {
-        let sample_rate = region.instrument.sample_sample_rate;
+    pub(crate) fn new(settings: &SynthesizerSettings, region: &ResolvedRegion) -> Self {
+        let sample_rate = region.get_sample_sample_rate();
         let loop_mode = region.get_sample_modes();
         let start = region.get_sample_start();
         let end = region.get_sample_end();
@@ -84,12 +87,26 @@ impl Oscillator {
     }
 
     fn fill_block_no_loop(&mut self, data: &[i16], block: &mut [f32], pitch_ratio_fp: i64) -> bool {
-        for t in 0..block.len() {
+        let block_length = block.len();
+        // The chunks which stay away from the end are interpolated at once.
+        let mut t = 0;
+        while t < block_length {
+            let last_fp = self.position_fp + (ArrayMath::LANES as i64 - 1) * pitch_ratio_fp;
+            let safe_end_fp = (self.end as i64 - 1) << Oscillator::FRAC_BITS;
+            if block_length - t < ArrayMath::LANES || last_fp >= safe_end_fp {
+                break;
+            }
+
+            self.fill_chunk(data, &mut block[t..t + ArrayMath::LANES], pitch_ratio_fp);
+            self.position_fp += ArrayMath::LANES as i64 * pitch_ratio_fp;
+            t += ArrayMath::LANES;
+        }
+
+        while t < block_length {
             let index = (self.position_fp >> Oscillator::FRAC_BITS) as usize;
             if index >= self.end as usize {
                 if t > 0 {
-                    let len = block.len();
-                    block[t..len].fill(0_f32);
+                    block[t..block_length].fill(0_f32);
                     return true;
                 } else {
                     return false;
@@ -103,6 +120,7 @@ impl Oscillator {
                 * ((x1 << Oscillator::FRAC_BITS) + a_fp * (x2 - x1)) as f32;
 
             self.position_fp += pitch_ratio_fp;
+            t += 1;
         }
 
         true
@@ -118,26 +136,53 @@ impl Oscillator {
         let loop_length = (self.end_loop - self.start_loop) as i64;
         let loop_length_fp = loop_length << Oscillator::FRAC_BITS;
 
-        for sample in block.iter_mut() {
+        for chunk in block.chunks_mut(ArrayMath::LANES) {
             if self.position_fp >= end_loop_fp {
                 self.position_fp -= loop_length_fp;
             }
 
-            let index1 = (self.position_fp >> Oscillator::FRAC_BITS) as usize;
-            let mut index2 = index1 + 1;
-            if index2 >= self.end_loop as usize {
-                index2 -= loop_length as usize;
+            // The chunks which do not reach the end of the loop are interpolated at once.
+            let last_fp = self.position_fp + (ArrayMath::LANES as i64 - 1) * pitch_ratio_fp;
+            if chunk.len() == ArrayMath::LANES && last_fp < end_loop_fp - Oscillator::FRAC_UNIT {
+                self.fill_chunk(data, chunk, pitch_ratio_fp);
+                self.position_fp += ArrayMath::LANES as i64 * pitch_ratio_fp;
+                continue;
             }
 
-            let x1 = data[index1] as i64;
-            let x2 = data[index2] as i64;
-            let a_fp = self.position_fp & (Oscillator::FRAC_UNIT - 1);
-            *sample = Oscillator::FP_TO_SAMPLE
-                * ((x1 << Oscillator::FRAC_BITS) + a_fp * (x2 - x1)) as f32;
+            for sample in chunk.iter_mut() {
+                if self.position_fp >= end_loop_fp {
+                    self.position_fp -= loop_length_fp;
+                }
 
-            self.position_fp += pitch_ratio_fp;
+                let index1 = (self.position_fp >> Oscillator::FRAC_BITS) as usize;
+                let mut index2 = index1 + 1;
+                if index2 >= self.end_loop as usize {
+                    index2 -= loop_length as usize;
+                }
+
+                let x1 = data[index1] as i64;
+                let x2 = data[index2] as i64;
+                let a_fp = self.position_fp & (Oscillator::FRAC_UNIT - 1);
+                *sample = Oscillator::FP_TO_SAMPLE
+                    * ((x1 << Oscillator::FRAC_BITS) + a_fp * (x2 - x1)) as f32;
+
+                self.position_fp += pitch_ratio_fp;
+            }
         }
 
         true
     }
+
+    /// Fills a chunk of `LANES` samples from the current position, without moving it.
+    fn fill_chunk(&self, data: &[i16], chunk: &mut [f32], pitch_ratio_fp: i64) {
+        if let Ok(chunk) = <&mut [f32; ArrayMath::LANES]>::try_from(chunk) {
+            ArrayMath::interpolate(
+                data,
+                self.position_fp,
+                pitch_ratio_fp,
+                Oscillator::SAMPLE_SCALE,
+                chunk,
+            );
+        }
+    }
 }
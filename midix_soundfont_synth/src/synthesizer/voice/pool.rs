@@ -0,0 +1,78 @@
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use bevy_platform::prelude::*;
+
+use crate::prelude::*;
+
+use super::Voice;
+
+/// The voices which are playing, with the blocks of those which are not.
+/// Everything is allocated up front, so that starting and stopping voices
+/// does not allocate or free memory.
+pub(crate) struct VoicePool {
+    voices: Vec<Voice>,
+    free_blocks: Vec<Vec<f32>>,
+}
+
+impl VoicePool {
+    pub(crate) fn new(settings: &SynthesizerSettings) -> Self {
+        let free_blocks = (0..settings.maximum_polyphony)
+            .map(|_| vec![0_f32; settings.block_size])
+            .collect();
+
+        Self {
+            voices: Vec::with_capacity(settings.maximum_polyphony),
+            free_blocks,
+        }
+    }
+
+    /// Adds a voice, which is created with a free block.
+    /// There must not be more voices than the maximum polyphony.
+    pub(crate) fn push(&mut self, create: impl FnOnce(Vec<f32>) -> Voice) {
+        if let Some(mut block) = self.free_blocks.pop() {
+            block.fill(0_f32);
+            self.voices.push(create(block));
+        }
+    }
+
+    /// Replaces a voice with one which is created with its block.
+    pub(crate) fn replace(&mut self, index: usize, create: impl FnOnce(Vec<f32>) -> Voice) {
+        let mut block = mem::take(&mut self.voices[index].block);
+        block.fill(0_f32);
+        self.voices[index] = create(block);
+    }
+
+    /// Removes the voices for which `keep` returns `false`, keeping the others in order.
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&Voice) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.voices.len() {
+            if keep(&self.voices[i]) {
+                self.voices.swap(kept, i);
+                kept += 1;
+            }
+        }
+
+        for voice in self.voices.drain(kept..) {
+            self.free_blocks.push(voice.block);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+}
+
+impl Deref for VoicePool {
+    type Target = [Voice];
+
+    fn deref(&self) -> &[Voice] {
+        &self.voices
+    }
+}
+
+impl DerefMut for VoicePool {
+    fn deref_mut(&mut self) -> &mut [Voice] {
+        &mut self.voices
+    }
+}
@@ -0,0 +1,55 @@
+use crate::prelude::*;
+
+/// Glides the pitch of a voice towards its key.
+///
+/// The offset is expressed in semitones relative to the key of the voice,
+/// and it moves linearly to zero over the portamento time.
+pub(crate) struct Portamento {
+    sample_rate: i32,
+    block_size: usize,
+
+    offset: f32,
+    step: f32,
+}
+
+impl Portamento {
+    pub(crate) fn new(settings: &SynthesizerSettings) -> Self {
+        Self {
+            sample_rate: settings.sample_rate,
+            block_size: settings.block_size,
+            offset: 0_f32,
+            step: 0_f32,
+        }
+    }
+
+    /// Starts a glide from `offset` semitones away from the key.
+    pub(crate) fn start(&mut self, offset: f32, time: f32) {
+        let block_count = time * self.sample_rate as f32 / self.block_size as f32;
+
+        self.offset = offset;
+        self.step = if block_count > 1_f32 {
+            offset.abs() / block_count
+        } else {
+            offset.abs()
+        };
+    }
+
+    pub(crate) fn stop(&mut self) {
+        self.offset = 0_f32;
+        self.step = 0_f32;
+    }
+
+    pub(crate) fn get_offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub(crate) fn process(&mut self) -> f32 {
+        if self.offset > 0_f32 {
+            self.offset = (self.offset - self.step).max(0_f32);
+        } else if self.offset < 0_f32 {
+            self.offset = (self.offset + self.step).min(0_f32);
+        }
+
+        self.offset
+    }
+}
@@ -2,3 +2,7 @@ mod pair;
 pub use pair::*;
 mod lfo;
 pub use lfo::*;
+mod offsets;
+pub(crate) use offsets::*;
+mod resolved;
+pub(crate) use resolved::*;
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+/// Offsets added to the generators of a region, in the units of the generators.
+///
+/// These are used to apply controllers such as the GS/XG NRPNs, which modify
+/// the parameters of the instrument rather than the sound being played.
+#[derive(Clone, Copy)]
+pub(crate) struct GeneratorOffsets {
+    values: [i32; GeneratorType::COUNT],
+}
+
+impl GeneratorOffsets {
+    pub(crate) const ZERO: GeneratorOffsets = GeneratorOffsets {
+        values: [0; GeneratorType::COUNT],
+    };
+
+    pub(crate) fn get(&self, generator: u16) -> i32 {
+        self.values[generator as usize]
+    }
+
+    pub(crate) fn add(&mut self, generator: u16, value: i32) {
+        self.values[generator as usize] += value;
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.values.iter().all(|value| *value == 0)
+    }
+}
@@ -1,17 +1,29 @@
 use crate::{prelude::*, utils};
 
+use super::GeneratorOffsets;
+
 pub struct RegionPair<'a> {
     pub preset: &'a PresetRegion,
     pub instrument: &'a InstrumentRegion,
+    offsets: &'a GeneratorOffsets,
 }
 
 impl<'a> RegionPair<'a> {
     pub fn new(preset: &'a PresetRegion, instrument: &'a InstrumentRegion) -> Self {
-        Self { preset, instrument }
+        Self {
+            preset,
+            instrument,
+            offsets: &GeneratorOffsets::ZERO,
+        }
+    }
+
+    /// Adds the given offsets to the generators of the region.
+    pub(crate) fn with_offsets(self, offsets: &'a GeneratorOffsets) -> Self {
+        Self { offsets, ..self }
     }
 
     fn gs(&self, i: usize) -> i32 {
-        self.preset.gs[i] as i32 + self.instrument.gs[i] as i32
+        self.preset.gs[i] as i32 + self.instrument.gs[i] as i32 + self.offsets.get(i as u16)
     }
 
     pub fn get_sample_start(&self) -> i32 {
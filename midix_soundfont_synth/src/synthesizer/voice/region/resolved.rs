@@ -0,0 +1,277 @@
+use super::RegionPair;
+
+use crate::prelude::*;
+
+/// The parameters of a pair of preset and instrument regions, resolved from the generators
+/// and converted to their units, so that starting a note only applies the key and velocity.
+pub(crate) struct ResolvedRegion {
+    sample_sample_rate: i32,
+    sample_start: i32,
+    sample_end: i32,
+    sample_start_loop: i32,
+    sample_end_loop: i32,
+    modulation_lfo_to_pitch: i32,
+    vibrato_lfo_to_pitch: i32,
+    modulation_envelope_to_pitch: i32,
+    initial_filter_cutoff_frequency: f32,
+    initial_filter_q: f32,
+    modulation_lfo_to_filter_cutoff_frequency: i32,
+    modulation_envelope_to_filter_cutoff_frequency: i32,
+    modulation_lfo_to_volume: f32,
+    chorus_effects_send: f32,
+    reverb_effects_send: f32,
+    pan: f32,
+    delay_modulation_lfo: f32,
+    frequency_modulation_lfo: f32,
+    delay_vibrato_lfo: f32,
+    frequency_vibrato_lfo: f32,
+    delay_modulation_envelope: f32,
+    attack_modulation_envelope: f32,
+    hold_modulation_envelope: f32,
+    decay_modulation_envelope: f32,
+    sustain_modulation_envelope: f32,
+    release_modulation_envelope: f32,
+    key_number_to_modulation_envelope_hold: i32,
+    key_number_to_modulation_envelope_decay: i32,
+    delay_volume_envelope: f32,
+    attack_volume_envelope: f32,
+    hold_volume_envelope: f32,
+    decay_volume_envelope: f32,
+    sustain_volume_envelope: f32,
+    release_volume_envelope: f32,
+    key_number_to_volume_envelope_hold: i32,
+    key_number_to_volume_envelope_decay: i32,
+    initial_attenuation: f32,
+    coarse_tune: i32,
+    fine_tune: i32,
+    sample_modes: LoopMode,
+    scale_tuning: i32,
+    exclusive_class: i32,
+    root_key: i32,
+}
+
+impl ResolvedRegion {
+    pub(crate) fn new(region: &RegionPair) -> Self {
+        Self {
+            sample_sample_rate: region.instrument.sample_sample_rate,
+            sample_start: region.get_sample_start(),
+            sample_end: region.get_sample_end(),
+            sample_start_loop: region.get_sample_start_loop(),
+            sample_end_loop: region.get_sample_end_loop(),
+            modulation_lfo_to_pitch: region.get_modulation_lfo_to_pitch(),
+            vibrato_lfo_to_pitch: region.get_vibrato_lfo_to_pitch(),
+            modulation_envelope_to_pitch: region.get_modulation_envelope_to_pitch(),
+            initial_filter_cutoff_frequency: region.get_initial_filter_cutoff_frequency(),
+            initial_filter_q: region.get_initial_filter_q(),
+            modulation_lfo_to_filter_cutoff_frequency: region
+                .get_modulation_lfo_to_filter_cutoff_frequency(),
+            modulation_envelope_to_filter_cutoff_frequency: region
+                .get_modulation_envelope_to_filter_cutoff_frequency(),
+            modulation_lfo_to_volume: region.get_modulation_lfo_to_volume(),
+            chorus_effects_send: region.get_chorus_effects_send(),
+            reverb_effects_send: region.get_reverb_effects_send(),
+            pan: region.get_pan(),
+            delay_modulation_lfo: region.get_delay_modulation_lfo(),
+            frequency_modulation_lfo: region.get_frequency_modulation_lfo(),
+            delay_vibrato_lfo: region.get_delay_vibrato_lfo(),
+            frequency_vibrato_lfo: region.get_frequency_vibrato_lfo(),
+            delay_modulation_envelope: region.get_delay_modulation_envelope(),
+            attack_modulation_envelope: region.get_attack_modulation_envelope(),
+            hold_modulation_envelope: region.get_hold_modulation_envelope(),
+            decay_modulation_envelope: region.get_decay_modulation_envelope(),
+            sustain_modulation_envelope: region.get_sustain_modulation_envelope(),
+            release_modulation_envelope: region.get_release_modulation_envelope(),
+            key_number_to_modulation_envelope_hold: region
+                .get_key_number_to_modulation_envelope_hold(),
+            key_number_to_modulation_envelope_decay: region
+                .get_key_number_to_modulation_envelope_decay(),
+            delay_volume_envelope: region.get_delay_volume_envelope(),
+            attack_volume_envelope: region.get_attack_volume_envelope(),
+            hold_volume_envelope: region.get_hold_volume_envelope(),
+            decay_volume_envelope: region.get_decay_volume_envelope(),
+            sustain_volume_envelope: region.get_sustain_volume_envelope(),
+            release_volume_envelope: region.get_release_volume_envelope(),
+            key_number_to_volume_envelope_hold: region.get_key_number_to_volume_envelope_hold(),
+            key_number_to_volume_envelope_decay: region.get_key_number_to_volume_envelope_decay(),
+            initial_attenuation: region.get_initial_attenuation(),
+            coarse_tune: region.get_coarse_tune(),
+            fine_tune: region.get_fine_tune(),
+            sample_modes: region.get_sample_modes(),
+            scale_tuning: region.get_scale_tuning(),
+            exclusive_class: region.get_exclusive_class(),
+            root_key: region.get_root_key(),
+        }
+    }
+
+    pub(crate) fn get_sample_sample_rate(&self) -> i32 {
+        self.sample_sample_rate
+    }
+
+    pub(crate) fn get_sample_start(&self) -> i32 {
+        self.sample_start
+    }
+
+    pub(crate) fn get_sample_end(&self) -> i32 {
+        self.sample_end
+    }
+
+    pub(crate) fn get_sample_start_loop(&self) -> i32 {
+        self.sample_start_loop
+    }
+
+    pub(crate) fn get_sample_end_loop(&self) -> i32 {
+        self.sample_end_loop
+    }
+
+    pub(crate) fn get_modulation_lfo_to_pitch(&self) -> i32 {
+        self.modulation_lfo_to_pitch
+    }
+
+    pub(crate) fn get_vibrato_lfo_to_pitch(&self) -> i32 {
+        self.vibrato_lfo_to_pitch
+    }
+
+    pub(crate) fn get_modulation_envelope_to_pitch(&self) -> i32 {
+        self.modulation_envelope_to_pitch
+    }
+
+    pub(crate) fn get_initial_filter_cutoff_frequency(&self) -> f32 {
+        self.initial_filter_cutoff_frequency
+    }
+
+    pub(crate) fn get_initial_filter_q(&self) -> f32 {
+        self.initial_filter_q
+    }
+
+    pub(crate) fn get_modulation_lfo_to_filter_cutoff_frequency(&self) -> i32 {
+        self.modulation_lfo_to_filter_cutoff_frequency
+    }
+
+    pub(crate) fn get_modulation_envelope_to_filter_cutoff_frequency(&self) -> i32 {
+        self.modulation_envelope_to_filter_cutoff_frequency
+    }
+
+    pub(crate) fn get_modulation_lfo_to_volume(&self) -> f32 {
+        self.modulation_lfo_to_volume
+    }
+
+    pub(crate) fn get_chorus_effects_send(&self) -> f32 {
+        self.chorus_effects_send
+    }
+
+    pub(crate) fn get_reverb_effects_send(&self) -> f32 {
+        self.reverb_effects_send
+    }
+
+    pub(crate) fn get_pan(&self) -> f32 {
+        self.pan
+    }
+
+    pub(crate) fn get_delay_modulation_lfo(&self) -> f32 {
+        self.delay_modulation_lfo
+    }
+
+    pub(crate) fn get_frequency_modulation_lfo(&self) -> f32 {
+        self.frequency_modulation_lfo
+    }
+
+    pub(crate) fn get_delay_vibrato_lfo(&self) -> f32 {
+        self.delay_vibrato_lfo
+    }
+
+    pub(crate) fn get_frequency_vibrato_lfo(&self) -> f32 {
+        self.frequency_vibrato_lfo
+    }
+
+    pub(crate) fn get_delay_modulation_envelope(&self) -> f32 {
+        self.delay_modulation_envelope
+    }
+
+    pub(crate) fn get_attack_modulation_envelope(&self) -> f32 {
+        self.attack_modulation_envelope
+    }
+
+    pub(crate) fn get_hold_modulation_envelope(&self) -> f32 {
+        self.hold_modulation_envelope
+    }
+
+    pub(crate) fn get_decay_modulation_envelope(&self) -> f32 {
+        self.decay_modulation_envelope
+    }
+
+    pub(crate) fn get_sustain_modulation_envelope(&self) -> f32 {
+        self.sustain_modulation_envelope
+    }
+
+    pub(crate) fn get_release_modulation_envelope(&self) -> f32 {
+        self.release_modulation_envelope
+    }
+
+    pub(crate) fn get_key_number_to_modulation_envelope_hold(&self) -> i32 {
+        self.key_number_to_modulation_envelope_hold
+    }
+
+    pub(crate) fn get_key_number_to_modulation_envelope_decay(&self) -> i32 {
+        self.key_number_to_modulation_envelope_decay
+    }
+
+    pub(crate) fn get_delay_volume_envelope(&self) -> f32 {
+        self.delay_volume_envelope
+    }
+
+    pub(crate) fn get_attack_volume_envelope(&self) -> f32 {
+        self.attack_volume_envelope
+    }
+
+    pub(crate) fn get_hold_volume_envelope(&self) -> f32 {
+        self.hold_volume_envelope
+    }
+
+    pub(crate) fn get_decay_volume_envelope(&self) -> f32 {
+        self.decay_volume_envelope
+    }
+
+    pub(crate) fn get_sustain_volume_envelope(&self) -> f32 {
+        self.sustain_volume_envelope
+    }
+
+    pub(crate) fn get_release_volume_envelope(&self) -> f32 {
+        self.release_volume_envelope
+    }
+
+    pub(crate) fn get_key_number_to_volume_envelope_hold(&self) -> i32 {
+        self.key_number_to_volume_envelope_hold
+    }
+
+    pub(crate) fn get_key_number_to_volume_envelope_decay(&self) -> i32 {
+        self.key_number_to_volume_envelope_decay
+    }
+
+    pub(crate) fn get_initial_attenuation(&self) -> f32 {
+        self.initial_attenuation
+    }
+
+    pub(crate) fn get_coarse_tune(&self) -> i32 {
+        self.coarse_tune
+    }
+
+    pub(crate) fn get_fine_tune(&self) -> i32 {
+        self.fine_tune
+    }
+
+    pub(crate) fn get_sample_modes(&self) -> LoopMode {
+        self.sample_modes
+    }
+
+    pub(crate) fn get_scale_tuning(&self) -> i32 {
+        self.scale_tuning
+    }
+
+    pub(crate) fn get_exclusive_class(&self) -> i32 {
+        self.exclusive_class
+    }
+
+    pub(crate) fn get_root_key(&self) -> i32 {
+        self.root_key
+    }
+}
@@ -0,0 +1,229 @@
+use core::any::Any;
+use core::mem;
+use core::panic::AssertUnwindSafe;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::panic;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use bevy_platform::prelude::*;
+
+// The worker threads wait for a job, which is a function called once with the index of each batch.
+// The thread which runs the job takes batches as well, and only returns once every worker
+// is done with the job, so that the job can borrow from its stack.
+// If the job panics on a worker, the panic is passed on to that thread once the others are done.
+// Waiting and waking up the threads does not allocate.
+
+type Job<'a> = dyn Fn(usize) + Sync + 'a;
+
+pub(crate) struct WorkerPool {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    job_ready: Condvar,
+    job_done: Condvar,
+    next_batch: AtomicUsize,
+}
+
+struct State {
+    generation: u64,
+    job: Option<*const Job<'static>>,
+    batch_count: usize,
+    running: usize,
+    panic: Option<Box<dyn Any + Send>>,
+    shutdown: bool,
+}
+
+// The job is only used while `WorkerPool::run` waits for it.
+unsafe impl Send for State {}
+
+impl WorkerPool {
+    pub(crate) fn new(worker_count: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                generation: 0,
+                job: None,
+                batch_count: 0,
+                running: 0,
+                panic: None,
+                shutdown: false,
+            }),
+            job_ready: Condvar::new(),
+            job_done: Condvar::new(),
+            next_batch: AtomicUsize::new(0),
+        });
+
+        let threads = (0..worker_count)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.work())
+            })
+            .collect();
+
+        Self { shared, threads }
+    }
+
+    /// Calls the job with every batch index from 0 to `batch_count`, spread over the threads,
+    /// and waits until all of them are done.
+    pub(crate) fn run(&self, batch_count: usize, job: &Job<'_>) {
+        // The lifetime of the job is erased, as no thread uses it after this returns.
+        let job = unsafe { mem::transmute::<&Job<'_>, *const Job<'static>>(job) };
+
+        {
+            let mut state = self.shared.lock();
+            self.shared.next_batch.store(0, Ordering::Relaxed);
+            state.job = Some(job);
+            state.batch_count = batch_count;
+            state.running = self.threads.len();
+            // A panic is left over if the thread which ran the last job panicked as well.
+            state.panic = None;
+            state.generation += 1;
+        }
+        self.shared.job_ready.notify_all();
+
+        {
+            // Even if the job panics, the workers must be done with it before the stack unwinds.
+            let _wait = WaitForWorkers(&self.shared);
+            unsafe { self.shared.take_batches(&*job, batch_count) };
+        }
+
+        if let Some(payload) = self.shared.lock().panic.take() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.job_ready.notify_all();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        let mut generation = 0;
+        loop {
+            let (job, batch_count) = {
+                let mut state = self.lock();
+                while state.generation == generation && !state.shutdown {
+                    state = self
+                        .job_ready
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                if state.shutdown {
+                    return;
+                }
+
+                generation = state.generation;
+                (state.job, state.batch_count)
+            };
+
+            // The worker keeps going after a panic, so that it is there for the next job.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                if let Some(job) = job {
+                    unsafe { self.take_batches(&*job, batch_count) };
+                }
+            }));
+
+            let mut state = self.lock();
+            if let Err(payload) = result {
+                state.panic.get_or_insert(payload);
+            }
+            state.running -= 1;
+            if state.running == 0 {
+                self.job_done.notify_one();
+            }
+        }
+    }
+
+    fn take_batches(&self, job: &Job<'_>, batch_count: usize) {
+        loop {
+            let batch = self.next_batch.fetch_add(1, Ordering::Relaxed);
+            if batch >= batch_count {
+                return;
+            }
+
+            job(batch);
+        }
+    }
+}
+
+struct WaitForWorkers<'a>(&'a Shared);
+
+impl Drop for WaitForWorkers<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        while state.running > 0 {
+            state = self
+                .0
+                .job_done
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        state.job = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::AtomicBool;
+
+    #[test]
+    fn runs_every_batch_once() {
+        let pool = WorkerPool::new(3);
+        let counts: Vec<AtomicUsize> = (0..100).map(|_| AtomicUsize::new(0)).collect();
+
+        for _ in 0..10 {
+            pool.run(counts.len(), &|batch| {
+                counts[batch].fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        assert!(
+            counts
+                .iter()
+                .all(|count| count.load(Ordering::Relaxed) == 10)
+        );
+    }
+
+    #[test]
+    fn passes_on_a_panic_and_keeps_working() {
+        let pool = WorkerPool::new(3);
+        let done: Vec<AtomicBool> = (0..100).map(|_| AtomicBool::new(false)).collect();
+
+        // The calling thread waits for a worker to panic.
+        let caller = thread::current().id();
+        let panicked = AtomicBool::new(false);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.run(done.len(), &|batch| {
+                if thread::current().id() == caller {
+                    while !panicked.load(Ordering::Relaxed) {
+                        thread::yield_now();
+                    }
+                } else if !panicked.swap(true, Ordering::Relaxed) {
+                    panic!("batch {batch}");
+                }
+            });
+        }));
+        assert!(result.is_err());
+
+        pool.run(done.len(), &|batch| {
+            done[batch].store(true, Ordering::Relaxed)
+        });
+        assert!(done.iter().all(|done| done.load(Ordering::Relaxed)));
+    }
+}
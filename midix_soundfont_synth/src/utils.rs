@@ -35,3 +35,33 @@ pub(crate) fn exp_cutoff(x: f64) -> f64 {
         x.exp()
     }
 }
+
+/// Scales a controller value up to a higher resolution, as MIDI 2.0 does when translating
+/// MIDI 1.0 messages. The minimum, the center and the maximum are mapped exactly.
+pub(crate) fn scale_up(value: u32, source_bits: u32, destination_bits: u32) -> u32 {
+    let scale_bits = destination_bits - source_bits;
+    let shifted = (value as u64) << scale_bits;
+
+    let center = 1_u32 << (source_bits - 1);
+    if value <= center {
+        return shifted as u32;
+    }
+
+    // Above the center, the lower bits are filled by repeating the value.
+    let repeat_bits = source_bits - 1;
+    let repeat_mask = (1_u64 << repeat_bits) - 1;
+    let mut repeat = value as u64 & repeat_mask;
+    if scale_bits > repeat_bits {
+        repeat <<= scale_bits - repeat_bits;
+    } else {
+        repeat >>= repeat_bits - scale_bits;
+    }
+
+    let mut result = shifted;
+    while repeat != 0 {
+        result |= repeat;
+        repeat >>= repeat_bits;
+    }
+
+    result as u32
+}
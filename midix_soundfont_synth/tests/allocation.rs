@@ -0,0 +1,102 @@
+//! Checks that the real-time calls do not allocate or free memory.
+//! The check is only done in debug builds, where the test process aborts if it fails.
+
+use std::sync::Arc;
+
+use midix_soundfont_synth::prelude::*;
+
+mod common;
+
+use common::create_sound_font_with_generators;
+
+#[global_allocator]
+static GLOBAL: GuardedAllocator = GuardedAllocator::new(std::alloc::System);
+
+const FRAMES: usize = 1000;
+
+fn create_synthesizer(output_effects: OutputEffects) -> Synthesizer {
+    // The exclusive class makes the notes of a channel replace each other.
+    let data = create_sound_font_with_generators(&[(57, 1)]);
+    let sound_font = Arc::new(SoundFont::new(&mut &data[..]).unwrap());
+    let mut settings = SynthesizerSettings::new(48000);
+    settings.maximum_polyphony = 32;
+    settings.output_count = 2;
+    settings.output_effects = output_effects;
+    settings.thread_count = 2;
+    Synthesizer::new(sound_font, &settings).unwrap()
+}
+
+/// Creates a MIDI Tuning Standard bulk dump, which tunes each key a quarter tone up.
+fn create_bulk_dump(program: u8) -> Vec<u8> {
+    let mut data = vec![0xF0, 0x7E, 0x7F, 0x08, 0x01, program];
+    data.extend(b"Quarter tone up ");
+    for key in 0..128_u8 {
+        data.extend([key, 0x40, 0x00]);
+    }
+    data.extend([0x00, 0xF7]);
+    data
+}
+
+/// Plays notes on every channel, more than the maximum polyphony,
+/// so that voices are stolen as well as replaced by their exclusive class.
+fn play_notes(synthesizer: &mut Synthesizer, step: usize) {
+    for i in 0..48 {
+        let channel = (i % 16) as u8;
+        let key = 36 + ((step * 7 + i * 5) % 48) as u8;
+        synthesizer.note_on(channel, key, 100);
+        if i % 3 == 0 {
+            synthesizer.note_off(channel, key);
+        }
+    }
+}
+
+#[test]
+fn render_does_not_allocate() {
+    const GS_RESET: [u8; 11] = [
+        0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, 0xF7,
+    ];
+
+    let mut synthesizer = create_synthesizer(OutputEffects::Shared);
+    // A channel which steals from itself.
+    synthesizer.set_channel_maximum_polyphony(1, Some(2));
+    let bulk_dumps: Vec<Vec<u8>> = (0..4).map(create_bulk_dump).collect();
+    let mut left = vec![0_f32; FRAMES];
+    let mut right = vec![0_f32; FRAMES];
+
+    for (step, bulk_dump) in bulk_dumps.iter().enumerate() {
+        play_notes(&mut synthesizer, step);
+        synthesizer.render(&mut left, &mut right);
+
+        synthesizer.process_sysex(bulk_dump);
+        synthesizer.process_sysex(&GS_RESET);
+        play_notes(&mut synthesizer, step);
+        synthesizer.render(&mut left, &mut right);
+    }
+
+    assert!(left.iter().any(|value| *value != 0_f32));
+}
+
+#[test]
+fn render_multi_does_not_allocate() {
+    for output_effects in [
+        OutputEffects::Shared,
+        OutputEffects::PerOutput,
+        OutputEffects::Sends,
+    ] {
+        let mut synthesizer = create_synthesizer(output_effects);
+        let mut outputs: Vec<[Vec<f32>; 2]> = (0..synthesizer.get_output_count())
+            .map(|_| [vec![0_f32; FRAMES], vec![0_f32; FRAMES]])
+            .collect();
+        let mut buffers: Vec<[&mut [f32]; 2]> = outputs
+            .iter_mut()
+            .map(|[left, right]| [&mut left[..], &mut right[..]])
+            .collect();
+
+        for step in 0..4 {
+            play_notes(&mut synthesizer, step);
+            synthesizer.render_multi(&mut buffers);
+        }
+
+        assert!(buffers[0][0].iter().any(|value| *value != 0_f32));
+    }
+}
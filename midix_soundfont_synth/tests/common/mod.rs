@@ -0,0 +1,114 @@
+//! A SoundFont which is built in memory, shared by the tests and the benchmarks.
+
+// Each test only uses some of the functions.
+#![allow(dead_code)]
+
+/// Creates a SoundFont with one preset, playing a looped sine wave over the whole keyboard.
+pub fn create_sound_font() -> Vec<u8> {
+    create_sound_font_with_generators(&[])
+}
+
+/// Creates the same SoundFont, with more generators in its instrument zone.
+pub fn create_sound_font_with_generators(generators: &[(u16, i16)]) -> Vec<u8> {
+    const SAMPLE_LENGTH: u32 = 4800;
+    const PERIOD: f32 = 100.0;
+    const SAMPLE_RATE: u32 = 48000;
+
+    let mut samples = Vec::new();
+    // The sample data ends with 46 zero samples, as the SoundFont specification requires.
+    for i in 0..SAMPLE_LENGTH + 46 {
+        let value = if i < SAMPLE_LENGTH {
+            (2.0 * std::f32::consts::PI * i as f32 / PERIOD).sin() * 16000.0
+        } else {
+            0.0
+        };
+        samples.extend((value as i16).to_le_bytes());
+    }
+
+    // Preset headers: name, patch, bank, bag index, then library, genre and morphology.
+    let mut phdr = Vec::new();
+    for (name, bag) in [("Sine", 0), ("EOP", 1)] {
+        phdr.extend(fixed_name(name));
+        phdr.extend(words(&[0, 0, bag]));
+        phdr.extend([0; 12]);
+    }
+
+    // Instrument headers: name, then bag index.
+    let mut inst = Vec::new();
+    for (name, bag) in [("Sine", 0), ("EOI", 1)] {
+        inst.extend(fixed_name(name));
+        inst.extend(words(&[bag]));
+    }
+
+    // Sample headers: name, start, end, start loop, end loop, sample rate,
+    // then original pitch, pitch correction, link and type.
+    let mut shdr = Vec::new();
+    shdr.extend(fixed_name("Sine"));
+    for value in [0, SAMPLE_LENGTH, 0, SAMPLE_LENGTH, SAMPLE_RATE] {
+        shdr.extend(value.to_le_bytes());
+    }
+    shdr.extend([60, 0]);
+    shdr.extend(words(&[0, 1]));
+    shdr.extend(fixed_name("EOS"));
+    shdr.extend([0; 26]);
+
+    // The given generators, loop continuously, use the sample, then the terminator.
+    let mut igen = Vec::new();
+    for &(generator, value) in generators {
+        igen.extend([generator, value as u16]);
+    }
+    igen.extend([54, 1, 53, 0, 0, 0]);
+
+    let info = list(b"INFO", &[chunk(b"ifil", &words(&[2, 1]))]);
+    let sdta = list(b"sdta", &[chunk(b"smpl", &samples)]);
+    let pdta = list(
+        b"pdta",
+        &[
+            chunk(b"phdr", &phdr),
+            // One zone with one generator, then the terminators.
+            chunk(b"pbag", &words(&[0, 0, 1, 0])),
+            chunk(b"pmod", &[0; 10]),
+            // The instrument generator, then the terminator.
+            chunk(b"pgen", &words(&[41, 0, 0, 0])),
+            chunk(b"inst", &inst),
+            chunk(b"ibag", &words(&[0, 0, igen.len() as u16 / 2 - 1, 0])),
+            chunk(b"imod", &[0; 10]),
+            chunk(b"igen", &words(&igen)),
+            chunk(b"shdr", &shdr),
+        ],
+    );
+
+    let mut data = b"sfbk".to_vec();
+    data.extend(info);
+    data.extend(sdta);
+    data.extend(pdta);
+    chunk(b"RIFF", &data)
+}
+
+fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend((data.len() as u32).to_le_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = list_type.to_vec();
+    for chunk in chunks {
+        data.extend(chunk);
+    }
+    self::chunk(b"LIST", &data)
+}
+
+fn fixed_name(name: &str) -> Vec<u8> {
+    let mut bytes = name.as_bytes().to_vec();
+    bytes.resize(20, 0);
+    bytes
+}
+
+fn words(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
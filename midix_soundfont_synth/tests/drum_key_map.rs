@@ -0,0 +1,117 @@
+//! Checks that the notes on a percussion channel stop with the drum key map,
+//! and that their per-note controllers follow the key of the note-on.
+
+use std::sync::Arc;
+
+use midix_soundfont_synth::prelude::*;
+
+mod common;
+
+use common::create_sound_font;
+
+const PERCUSSION_CHANNEL: u8 = Synthesizer::PERCUSSION_CHANNEL as u8;
+
+fn create_synthesizer() -> Synthesizer {
+    let sound_font = Arc::new(SoundFont::new(&mut &create_sound_font()[..]).unwrap());
+    let mut settings = SynthesizerSettings::new(48000);
+    // The tail of the effects would hide whether the notes have stopped.
+    settings.enable_reverb_and_chorus = false;
+    Synthesizer::new(sound_font, &settings).unwrap()
+}
+
+/// Renders a tenth of a second, and returns the peak of the last block.
+fn render_peak(synthesizer: &mut Synthesizer) -> f32 {
+    let mut left = vec![0_f32; 4800];
+    let mut right = vec![0_f32; 4800];
+    synthesizer.render(&mut left, &mut right);
+
+    let last = left.len() - synthesizer.get_block_size();
+    left[last..]
+        .iter()
+        .chain(&right[last..])
+        .fold(0_f32, |peak, value| peak.max(value.abs()))
+}
+
+#[test]
+fn note_stops_after_the_map_changes() {
+    let mut synthesizer = create_synthesizer();
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 60);
+    synthesizer.note_on(PERCUSSION_CHANNEL, 40, 100);
+    assert!(render_peak(&mut synthesizer) > 0.01_f32);
+
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 62);
+    synthesizer.note_off(PERCUSSION_CHANNEL, 40);
+    assert!(render_peak(&mut synthesizer) < 1.0E-6_f32);
+}
+
+#[test]
+fn keys_mapped_to_the_same_drum_stop_separately() {
+    let mut synthesizer = create_synthesizer();
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 60);
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 41, 60);
+    synthesizer.note_on(PERCUSSION_CHANNEL, 40, 100);
+    synthesizer.note_on(PERCUSSION_CHANNEL, 41, 100);
+
+    synthesizer.note_off(PERCUSSION_CHANNEL, 41);
+    assert!(render_peak(&mut synthesizer) > 0.01_f32);
+
+    synthesizer.note_off(PERCUSSION_CHANNEL, 40);
+    assert!(render_peak(&mut synthesizer) < 1.0E-6_f32);
+}
+
+/// Creates a MIDI 2.0 channel voice message on the first group.
+fn midi2(status: u32, channel: u8, index: u8, low: u8, data: u32) -> [u32; 2] {
+    let word0 = (0x4 << 28)
+        | (status << 20)
+        | ((channel as u32) << 16)
+        | ((index as u32) << 8)
+        | low as u32;
+    [word0, data]
+}
+
+fn midi2_note_on(synthesizer: &mut Synthesizer, key: u8) {
+    synthesizer.process_universal_midi_packet(&midi2(
+        0x9,
+        PERCUSSION_CHANNEL,
+        key,
+        0,
+        0xC000 << 16,
+    ));
+}
+
+/// Sets the per-note volume (registered per-note controller 7) to zero.
+fn midi2_mute(synthesizer: &mut Synthesizer, key: u8) {
+    synthesizer.process_universal_midi_packet(&midi2(0x0, PERCUSSION_CHANNEL, key, 7, 0));
+}
+
+#[test]
+fn per_note_controllers_follow_the_key_of_the_note_on() {
+    let mut single = create_synthesizer();
+    single.set_drum_key(PERCUSSION_CHANNEL, 40, 60);
+    midi2_note_on(&mut single, 40);
+    let expected = render_peak(&mut single);
+
+    // The notes share a drum key, but only the second one is muted.
+    let mut synthesizer = create_synthesizer();
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 60);
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 41, 60);
+    midi2_note_on(&mut synthesizer, 40);
+    midi2_note_on(&mut synthesizer, 41);
+    midi2_mute(&mut synthesizer, 41);
+    let actual = render_peak(&mut synthesizer);
+
+    assert!(expected > 0.01_f32);
+    assert!((actual - expected).abs() < 1.0E-4_f32);
+}
+
+#[test]
+fn per_note_controllers_apply_after_the_map_changes() {
+    let mut synthesizer = create_synthesizer();
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 60);
+    midi2_note_on(&mut synthesizer, 40);
+    assert!(render_peak(&mut synthesizer) > 0.01_f32);
+
+    synthesizer.set_drum_key(PERCUSSION_CHANNEL, 40, 62);
+    midi2_mute(&mut synthesizer, 40);
+    assert!(render_peak(&mut synthesizer) < 1.0E-6_f32);
+}
@@ -0,0 +1,100 @@
+//! Checks that rendering to multiple outputs splits the same mix as rendering to one.
+
+use std::sync::Arc;
+
+use midix_soundfont_synth::prelude::*;
+
+mod common;
+
+use common::create_sound_font;
+
+const OUTPUT_COUNT: usize = 3;
+
+// Not a multiple of the block size, so that the blocks are split between the calls.
+const FRAMES: usize = 1000;
+
+fn create_synthesizer(
+    enable_reverb_and_chorus: bool,
+    output_effects: OutputEffects,
+) -> Synthesizer {
+    let sound_font = Arc::new(SoundFont::new(&mut &create_sound_font()[..]).unwrap());
+    let mut settings = SynthesizerSettings::new(48000);
+    settings.enable_reverb_and_chorus = enable_reverb_and_chorus;
+    settings.output_count = OUTPUT_COUNT;
+    settings.output_effects = output_effects;
+    let mut synthesizer = Synthesizer::new(sound_font, &settings).unwrap();
+
+    // Two channels share the first output, and one output has no channel playing.
+    synthesizer.set_channel_output(3, 0);
+    for (channel, key) in [(0, 60), (1, 64), (3, 67)] {
+        synthesizer.note_on(channel, key, 100);
+    }
+    synthesizer
+}
+
+fn render(synthesizer: &mut Synthesizer) -> [Vec<f32>; 2] {
+    let mut left = vec![0_f32; FRAMES];
+    let mut right = vec![0_f32; FRAMES];
+    synthesizer.render(&mut left, &mut right);
+    [left, right]
+}
+
+fn render_multi(synthesizer: &mut Synthesizer) -> Vec<[Vec<f32>; 2]> {
+    let mut outputs: Vec<[Vec<f32>; 2]> = (0..synthesizer.get_output_count())
+        .map(|_| [vec![0_f32; FRAMES], vec![0_f32; FRAMES]])
+        .collect();
+    let mut buffers: Vec<[&mut [f32]; 2]> = outputs
+        .iter_mut()
+        .map(|[left, right]| [&mut left[..], &mut right[..]])
+        .collect();
+    synthesizer.render_multi(&mut buffers);
+    outputs
+}
+
+#[test]
+fn channel_outputs_sum_to_render_without_effects() {
+    for output_effects in [
+        OutputEffects::Shared,
+        OutputEffects::PerOutput,
+        OutputEffects::Sends,
+    ] {
+        let mut single = create_synthesizer(false, output_effects);
+        let mut multi = create_synthesizer(false, output_effects);
+
+        for _ in 0..10 {
+            let expected = render(&mut single);
+            let outputs = render_multi(&mut multi);
+
+            for (i, expected) in expected.iter().enumerate() {
+                assert!(expected.iter().any(|value| *value != 0_f32));
+
+                for (t, expected) in expected.iter().enumerate() {
+                    let sum: f32 = outputs[..OUTPUT_COUNT]
+                        .iter()
+                        .map(|output| output[i][t])
+                        .sum();
+                    assert!(
+                        (sum - expected).abs() < 1.0E-6_f32,
+                        "{output_effects:?}: expected {expected}, got {sum}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn render_keeps_the_effects_with_sends() {
+    let mut shared = create_synthesizer(true, OutputEffects::Shared);
+    let mut sends = create_synthesizer(true, OutputEffects::Sends);
+    let mut dry = create_synthesizer(false, OutputEffects::Sends);
+    assert!(sends.get_enable_reverb_and_chorus());
+
+    let mut wet = false;
+    for _ in 0..10 {
+        let output = render(&mut sends);
+        assert_eq!(output, render(&mut shared));
+        wet |= output != render(&mut dry);
+    }
+    assert!(wet);
+}
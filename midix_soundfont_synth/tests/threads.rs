@@ -0,0 +1,56 @@
+//! Checks that the worker threads do not change the output.
+
+use std::sync::Arc;
+
+use midix_soundfont_synth::prelude::*;
+
+mod common;
+
+use common::create_sound_font;
+
+const FRAMES: usize = 512;
+
+fn render_notes(thread_count: usize) -> Vec<f32> {
+    let sound_font = Arc::new(SoundFont::new(&mut &create_sound_font()[..]).unwrap());
+    let mut settings = SynthesizerSettings::new(48000);
+    settings.maximum_polyphony = 128;
+    settings.thread_count = thread_count;
+    let mut synthesizer = Synthesizer::new(sound_font, &settings).unwrap();
+
+    let mut left = vec![0_f32; FRAMES];
+    let mut right = vec![0_f32; FRAMES];
+    let mut output = Vec::new();
+    for step in 0..40 {
+        // Start and stop notes on every channel, so that the voices come and go between blocks.
+        for i in 0..8 {
+            let channel = ((step + i) % 16) as u8;
+            let key = 36 + ((step * 7 + i * 5) % 48) as u8;
+            synthesizer.note_on(channel, key, 40 + (i * 10) as u8);
+            if step >= 4 {
+                let old_key = 36 + (((step - 4) * 7 + i * 5) % 48) as u8;
+                let old_channel = ((step - 4 + i) % 16) as u8;
+                synthesizer.note_off(old_channel, old_key);
+            }
+        }
+
+        synthesizer.render(&mut left, &mut right);
+        output.extend(&left);
+        output.extend(&right);
+    }
+
+    output
+}
+
+#[test]
+fn threads_render_the_same_output() {
+    let single = render_notes(1);
+    let multi = render_notes(4);
+
+    assert!(single.iter().any(|value| *value != 0_f32));
+    assert!(
+        single
+            .iter()
+            .zip(&multi)
+            .all(|(a, b)| a.to_bits() == b.to_bits())
+    );
+}
@@ -0,0 +1,285 @@
+use bevy::prelude::*;
+use bevy_seedling::node::RegisterNode;
+use firewheel::{
+    channel_config::{ChannelConfig, ChannelCount},
+    diff::{Diff, Patch},
+    event::ProcEvents,
+    node::{
+        AudioNode, AudioNodeInfo, AudioNodeProcessor, ConstructProcessorContext, EmptyConfig,
+        ProcBuffers, ProcExtra, ProcInfo, ProcessStatus,
+    },
+};
+use midix_soundfont_synth::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_node::<SynthReverbNode>();
+    app.register_node::<SynthChorusNode>();
+}
+
+/// The reverb of the synthesizer, as a node which can process any stereo signal.
+///
+/// Only the reverb is output by default, so that the node can be used as a send bus.
+#[derive(Debug, Clone, Copy, PartialEq, Diff, Patch, Component)]
+pub struct SynthReverbNode {
+    /// The size of the room, from 0 to 1
+    pub room_size: f32,
+    /// The time for the reverb to decay by 60 dB, in seconds
+    pub decay_time: f32,
+    /// The damping of the high frequencies, from 0 to 1
+    pub damp: f32,
+    /// The stereo width, from 0 (mono) to 1
+    pub width: f32,
+    /// The level of the reverb, from 0 to 1
+    pub wet: f32,
+    /// The delay before the reverb starts, in seconds
+    pub pre_delay: f32,
+    /// The level of the unprocessed input
+    pub dry: f32,
+}
+
+impl Default for SynthReverbNode {
+    fn default() -> Self {
+        Self::from_parameters(&ReverbParameters::default())
+    }
+}
+
+impl SynthReverbNode {
+    /// Create a new node from reverb parameters, such as those of a [`ReverbPreset`]
+    pub fn from_parameters(parameters: &ReverbParameters) -> Self {
+        Self {
+            room_size: parameters.room_size,
+            decay_time: parameters.decay_time,
+            damp: parameters.damp,
+            width: parameters.width,
+            wet: parameters.wet,
+            pre_delay: parameters.pre_delay,
+            dry: 0.0,
+        }
+    }
+
+    /// Get the reverb parameters of the node
+    pub fn get_parameters(&self) -> ReverbParameters {
+        ReverbParameters {
+            room_size: self.room_size,
+            decay_time: self.decay_time,
+            damp: self.damp,
+            width: self.width,
+            wet: self.wet,
+            pre_delay: self.pre_delay,
+        }
+    }
+}
+
+/// Configuration for the reverb node
+#[derive(Debug, Clone, Default, PartialEq, Component)]
+pub struct SynthReverbConfig {
+    /// The reverb engine
+    pub backend: ReverbBackend,
+}
+
+impl AudioNode for SynthReverbNode {
+    type Configuration = SynthReverbConfig;
+
+    fn info(&self, _config: &Self::Configuration) -> AudioNodeInfo {
+        AudioNodeInfo::new()
+            .debug_name("Synth Reverb")
+            .channel_config(ChannelConfig {
+                num_inputs: ChannelCount::STEREO,
+                num_outputs: ChannelCount::STEREO,
+            })
+    }
+
+    fn construct_processor(
+        &self,
+        config: &Self::Configuration,
+        cx: ConstructProcessorContext,
+    ) -> impl AudioNodeProcessor {
+        let mut reverb = Reverb::new(
+            cx.stream_info.sample_rate.get() as i32,
+            cx.stream_info.max_block_frames.get() as usize,
+            &config.backend,
+        );
+        reverb.set_parameters(&self.get_parameters());
+
+        SynthReverbProcessor {
+            params: *self,
+            reverb,
+        }
+    }
+}
+
+/// Reverb audio node processor
+struct SynthReverbProcessor {
+    params: SynthReverbNode,
+    reverb: Reverb,
+}
+
+impl AudioNodeProcessor for SynthReverbProcessor {
+    fn process(
+        &mut self,
+        info: &ProcInfo,
+        ProcBuffers {
+            inputs, outputs, ..
+        }: ProcBuffers,
+        events: &mut ProcEvents,
+        _extra: &mut ProcExtra,
+    ) -> ProcessStatus {
+        let mut changed = false;
+        for patch in events.drain_patches::<SynthReverbNode>() {
+            self.params.apply(patch);
+            changed = true;
+        }
+
+        if changed {
+            self.reverb.set_parameters(&self.params.get_parameters());
+        }
+
+        let frames = info.frames;
+
+        // guaranteed to be 2 due to our node's STEREO value.
+        let (left, right) = outputs.split_at_mut(1);
+        self.reverb.process(
+            &inputs[0][..frames],
+            &inputs[1][..frames],
+            &mut left[0][..frames],
+            &mut right[0][..frames],
+        );
+        mix_dry(self.params.dry, inputs, outputs, frames);
+
+        // The tail keeps ringing after the input goes silent.
+        ProcessStatus::outputs_not_silent()
+    }
+}
+
+/// The chorus of the synthesizer, as a node which can process any stereo signal.
+///
+/// Only the chorus is output by default, so that the node can be used as a send bus.
+#[derive(Debug, Clone, Copy, PartialEq, Diff, Patch, Component)]
+pub struct SynthChorusNode {
+    /// The center delay, in seconds
+    pub delay: f32,
+    /// The modulation depth of the delay, in seconds
+    pub depth: f32,
+    /// The modulation rate, in Hz
+    pub rate: f32,
+    /// The amount of the output fed back to the input, from 0 to less than 1
+    pub feedback: f32,
+    /// The level of the chorus
+    pub level: f32,
+    /// The level of the unprocessed input
+    pub dry: f32,
+}
+
+impl Default for SynthChorusNode {
+    fn default() -> Self {
+        Self::from_parameters(&ChorusParameters::default())
+    }
+}
+
+impl SynthChorusNode {
+    /// Create a new node from chorus parameters, such as those of a [`ChorusPreset`]
+    pub fn from_parameters(parameters: &ChorusParameters) -> Self {
+        Self {
+            delay: parameters.delay,
+            depth: parameters.depth,
+            rate: parameters.rate,
+            feedback: parameters.feedback,
+            level: parameters.level,
+            dry: 0.0,
+        }
+    }
+
+    /// Get the chorus parameters of the node
+    pub fn get_parameters(&self) -> ChorusParameters {
+        ChorusParameters {
+            delay: self.delay,
+            depth: self.depth,
+            rate: self.rate,
+            feedback: self.feedback,
+            level: self.level,
+        }
+    }
+}
+
+impl AudioNode for SynthChorusNode {
+    type Configuration = EmptyConfig;
+
+    fn info(&self, _config: &Self::Configuration) -> AudioNodeInfo {
+        AudioNodeInfo::new()
+            .debug_name("Synth Chorus")
+            .channel_config(ChannelConfig {
+                num_inputs: ChannelCount::STEREO,
+                num_outputs: ChannelCount::STEREO,
+            })
+    }
+
+    fn construct_processor(
+        &self,
+        _config: &Self::Configuration,
+        cx: ConstructProcessorContext,
+    ) -> impl AudioNodeProcessor {
+        SynthChorusProcessor {
+            params: *self,
+            chorus: Chorus::new(
+                cx.stream_info.sample_rate.get() as i32,
+                &self.get_parameters(),
+            ),
+        }
+    }
+}
+
+/// Chorus audio node processor
+struct SynthChorusProcessor {
+    params: SynthChorusNode,
+    chorus: Chorus,
+}
+
+impl AudioNodeProcessor for SynthChorusProcessor {
+    fn process(
+        &mut self,
+        info: &ProcInfo,
+        ProcBuffers {
+            inputs, outputs, ..
+        }: ProcBuffers,
+        events: &mut ProcEvents,
+        _extra: &mut ProcExtra,
+    ) -> ProcessStatus {
+        let mut changed = false;
+        for patch in events.drain_patches::<SynthChorusNode>() {
+            self.params.apply(patch);
+            changed = true;
+        }
+
+        if changed {
+            self.chorus.set_parameters(&self.params.get_parameters());
+        }
+
+        let frames = info.frames;
+
+        // guaranteed to be 2 due to our node's STEREO value.
+        let (left, right) = outputs.split_at_mut(1);
+        self.chorus.process(
+            &inputs[0][..frames],
+            &inputs[1][..frames],
+            &mut left[0][..frames],
+            &mut right[0][..frames],
+        );
+        mix_dry(self.params.dry, inputs, outputs, frames);
+
+        // The delay line keeps sounding after the input goes silent.
+        ProcessStatus::outputs_not_silent()
+    }
+}
+
+/// Add the unprocessed input to the output
+fn mix_dry(dry: f32, inputs: &[&[f32]], outputs: &mut [&mut [f32]], frames: usize) {
+    if dry <= 0.0 {
+        return;
+    }
+
+    for (input, output) in inputs.iter().zip(outputs.iter_mut()) {
+        for (out, sample) in output[..frames].iter_mut().zip(&input[..frames]) {
+            *out += dry * sample;
+        }
+    }
+}
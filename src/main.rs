@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Duration};
+use std::{alloc::System, sync::Arc, time::Duration};
 
 use bevy::{prelude::*, time::common_conditions::on_timer};
 use bevy_seedling::{
@@ -23,12 +23,22 @@ use crate::{
     soundfont::SoundFontAsset,
 };
 
+mod effects;
 mod player;
 mod soundfont;
 
+// In debug builds, this aborts if the synthesizer allocates memory on the audio thread.
+#[global_allocator]
+static ALLOCATOR: GuardedAllocator = GuardedAllocator::new(System);
+
 fn main() -> AppExit {
     let mut app = App::new();
-    app.add_plugins((DefaultPlugins, SeedlingPlugin::default(), soundfont::plugin));
+    app.add_plugins((
+        DefaultPlugins,
+        SeedlingPlugin::default(),
+        soundfont::plugin,
+        effects::plugin,
+    ));
 
     //intentionally registering a simple node
     app.register_simple_node::<MidiSynthNode>();
@@ -120,6 +130,12 @@ pub struct MidiSynthNode {
     pub soundfont: Arc<SoundFont>,
     /// Enable reverb and chorus
     pub enable_reverb_and_chorus: bool,
+    /// Output the reverb and chorus sends instead of the built-in effects
+    ///
+    /// The node then has three stereo outputs: the dry mix on channels 0 and 1,
+    /// the reverb send on channels 2 and 3, and the chorus send on channels 4 and 5.
+    /// These can be connected to shared effect buses, such as [`SynthReverbNode`](crate::effects::SynthReverbNode).
+    pub effects_as_aux_outputs: bool,
 }
 
 impl MidiSynthNode {
@@ -128,19 +144,33 @@ impl MidiSynthNode {
         Self {
             soundfont,
             enable_reverb_and_chorus,
+            effects_as_aux_outputs: false,
         }
     }
+
+    /// Output the reverb and chorus sends to aux outputs instead of the built-in effects
+    pub fn with_effects_as_aux_outputs(mut self) -> Self {
+        self.effects_as_aux_outputs = true;
+        self
+    }
 }
 
 impl AudioNode for MidiSynthNode {
     type Configuration = EmptyConfig;
 
     fn info(&self, _config: &Self::Configuration) -> AudioNodeInfo {
+        let num_outputs = if self.effects_as_aux_outputs {
+            // dry, reverb send and chorus send
+            ChannelCount::new(6).unwrap()
+        } else {
+            ChannelCount::STEREO
+        };
+
         AudioNodeInfo::new()
             .debug_name("MIDI Synthesizer")
             .channel_config(ChannelConfig {
                 num_inputs: ChannelCount::ZERO,
-                num_outputs: ChannelCount::STEREO,
+                num_outputs,
             })
     }
 
@@ -156,6 +186,7 @@ impl AudioNode for MidiSynthNode {
 /// MIDI synthesizer audio node processor
 pub struct MidiSynthProcessor {
     synthesizer: Synthesizer,
+    effects_as_aux_outputs: bool,
 }
 
 impl MidiSynthProcessor {
@@ -163,11 +194,17 @@ impl MidiSynthProcessor {
     pub fn new(config: &MidiSynthNode, cx: ConstructProcessorContext) -> Self {
         let mut settings = SynthesizerSettings::new(cx.stream_info.sample_rate.get() as i32);
         settings.enable_reverb_and_chorus = config.enable_reverb_and_chorus;
+        if config.effects_as_aux_outputs {
+            settings.output_effects = OutputEffects::Sends;
+        }
 
         let synthesizer = Synthesizer::new(config.soundfont.clone(), &settings)
             .expect("Failed to create synthesizer");
 
-        Self { synthesizer }
+        Self {
+            synthesizer,
+            effects_as_aux_outputs: config.effects_as_aux_outputs,
+        }
     }
 
     /// Process a MIDI command
@@ -199,6 +236,19 @@ impl AudioNodeProcessor for MidiSynthProcessor {
 
         let frames = info.frames;
 
+        if self.effects_as_aux_outputs {
+            // guaranteed to be 6 due to our node's channel config.
+            let [dry_l, dry_r, reverb_l, reverb_r, chorus_l, chorus_r] = outputs else {
+                return ProcessStatus::ClearAllOutputs;
+            };
+            self.synthesizer.render_multi(&mut [
+                [&mut dry_l[..frames], &mut dry_r[..frames]],
+                [&mut reverb_l[..frames], &mut reverb_r[..frames]],
+                [&mut chorus_l[..frames], &mut chorus_r[..frames]],
+            ]);
+            return ProcessStatus::outputs_not_silent();
+        }
+
         // guaranteed to be 2 due to our node's STEREO value.
         let (left, right) = outputs.split_at_mut(1);
         // Render audio from the synthesizer